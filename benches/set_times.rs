@@ -0,0 +1,34 @@
+//! Measures the throughput of setting a single time of a file, which is a
+//! single `utimensat` call with `UTIME_OMIT` where that's available, but a
+//! `stat` followed by `utimes` on the fallback path.
+//!
+//! Run with `cargo bench`, optionally with `--features force-utimes` on Linux
+//! to compare against the fallback.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use filetime::{set_file_handle_times, set_file_mtime, FileTime};
+use std::fs::File;
+
+fn set_times(c: &mut Criterion) {
+    let td = tempfile::Builder::new()
+        .prefix("filetime")
+        .tempdir()
+        .unwrap();
+    let path = td.path().join("foo.txt");
+    let file = File::create(&path).unwrap();
+    let mut seconds = 1_000_000;
+    let mut time = move || {
+        seconds += 1;
+        FileTime::from_unix_time(seconds, 0)
+    };
+
+    c.bench_function("set_file_mtime", |b| {
+        b.iter(|| set_file_mtime(&path, time()).unwrap())
+    });
+    c.bench_function("set_file_handle_times", |b| {
+        b.iter(|| set_file_handle_times(&file, None, Some(time())).unwrap())
+    });
+}
+
+criterion_group!(benches, set_times);
+criterion_main!(benches);
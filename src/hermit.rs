@@ -0,0 +1,75 @@
+//! Hermit has no interface for setting the times of a file, so all of the
+//! setters return an error of kind `io::ErrorKind::Unsupported`. The access
+//! and modification times can still be read through `std`.
+
+use crate::FileTime;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use std::time::SystemTime;
+
+fn unsupported() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "setting file times is not supported on Hermit",
+    )
+}
+
+pub fn set_file_times(_p: &Path, _atime: FileTime, _mtime: FileTime) -> io::Result<()> {
+    Err(unsupported())
+}
+
+pub fn set_symlink_file_times(_p: &Path, _atime: FileTime, _mtime: FileTime) -> io::Result<()> {
+    Err(unsupported())
+}
+
+pub fn set_file_mtime(_p: &Path, _mtime: FileTime) -> io::Result<()> {
+    Err(unsupported())
+}
+
+pub fn set_file_atime(_p: &Path, _atime: FileTime) -> io::Result<()> {
+    Err(unsupported())
+}
+
+pub fn set_file_handle_times(
+    _f: &File,
+    _atime: Option<FileTime>,
+    _mtime: Option<FileTime>,
+) -> io::Result<()> {
+    Err(unsupported())
+}
+
+pub fn supports_nanosecond_times() -> bool {
+    false
+}
+
+pub fn is_unsupported_error(e: &io::Error) -> bool {
+    e.kind() == io::ErrorKind::Unsupported
+}
+
+pub fn check_range(_ft: &FileTime) -> io::Result<()> {
+    Ok(())
+}
+
+pub const SUPPORTS_CREATION_TIME: bool = false;
+
+fn from_system_time(time: io::Result<SystemTime>) -> FileTime {
+    time.map(FileTime::from_system_time)
+        .unwrap_or_else(|_| FileTime::zero())
+}
+
+pub fn from_last_modification_time(meta: &fs::Metadata) -> FileTime {
+    from_system_time(meta.modified())
+}
+
+pub fn from_last_access_time(meta: &fs::Metadata) -> FileTime {
+    from_system_time(meta.accessed())
+}
+
+pub fn from_status_change_time(_meta: &fs::Metadata) -> Option<FileTime> {
+    None
+}
+
+pub fn from_creation_time(_meta: &fs::Metadata) -> Option<FileTime> {
+    None
+}
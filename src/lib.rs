@@ -33,11 +33,24 @@
 //! // Print the platform-specific value of seconds
 //! println!("{}", mtime.seconds());
 //! ```
+//!
+//! Note that only access, modification and, on Windows, creation times can be
+//! set. The status change time (`ctime`) on Unix can't be set by userspace and
+//! is updated to the current time whenever the other times are set; see
+//! `can_set_ctime`.
+//!
+//! On Hermit the access and modification times can be read, but every
+//! function which sets times returns an error of kind
+//! `io::ErrorKind::Unsupported`. On Horizon times can be set by path with
+//! microsecond precision, but not through a file handle or of a symlink
+//! itself.
 
+use std::cmp::Ordering;
+use std::convert::TryFrom;
 use std::fmt;
 use std::fs;
 use std::io;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 cfg_if::cfg_if! {
@@ -50,18 +63,33 @@ cfg_if::cfg_if! {
     } else if #[cfg(all(target_family = "wasm", not(target_os = "emscripten")))] {
         #[path = "wasm.rs"]
         mod imp;
+    } else if #[cfg(target_os = "hermit")] {
+        #[path = "hermit.rs"]
+        mod imp;
     } else {
         #[path = "unix/mod.rs"]
         mod imp;
     }
 }
 
+// The module is empty on targets whose values haven't been checked.
+#[cfg(all(unix, not(target_os = "redox")))]
+#[allow(unused_imports)]
+pub use crate::imp::utime::*;
+
 /// A helper structure to represent a timestamp for a file.
 ///
 /// The actual value contined within is platform-specific and does not have the
 /// same meaning across platforms, but comparisons and stringification can be
 /// significant among the same platform.
-#[derive(Eq, PartialEq, Ord, PartialOrd, Debug, Copy, Clone, Hash)]
+///
+/// The derived `Ord` implementation compares the raw platform-relative
+/// values. Use `cmp_normalized` to explicitly compare timestamps relative to
+/// the Unix epoch.
+///
+/// The `Debug` representation shows both the time relative to the Unix epoch
+/// and the raw platform-relative seconds.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash)]
 pub struct FileTime {
     seconds: i64,
     nanos: u32,
@@ -78,6 +106,16 @@ impl FileTime {
         }
     }
 
+    /// Returns whether this is the same time as `FileTime::zero()`.
+    ///
+    /// Many archive formats and filesystems use a zero timestamp to mean that
+    /// a time isn't set. Note that this checks the platform's raw zero, which
+    /// is the Unix epoch, 1970-01-01T00:00:00Z, on most platforms but the
+    /// Windows epoch, 1601-01-01T00:00:00Z, on Windows.
+    pub const fn is_zero(&self) -> bool {
+        self.seconds == 0 && self.nanos == 0
+    }
+
     const fn emulate_second_only_system(self) -> FileTime {
         if cfg!(emulate_second_only_system) {
             FileTime {
@@ -103,36 +141,146 @@ impl FileTime {
     /// # }
     /// ```
     ///
-    /// Equivalent to `FileTime::from_system_time(SystemTime::now())`.
+    /// Equivalent to `FileTime::from_system_time(SystemTime::now())`, except
+    /// on Windows where `GetSystemTimeAsFileTime` is used. That's cheaper than
+    /// the clock used by `SystemTime`, but only updated every clock tick,
+    /// typically 15.6ms. Use `now_precise` where that's too coarse.
     pub fn now() -> FileTime {
-        FileTime::from_system_time(SystemTime::now())
+        cfg_if::cfg_if! {
+            if #[cfg(windows)] {
+                imp::now().emulate_second_only_system()
+            } else {
+                FileTime::from_system_time(SystemTime::now())
+            }
+        }
+    }
+
+    /// Creates a new timestamp representing the current system time with the
+    /// highest precision available.
+    ///
+    /// On Windows this uses `GetSystemTimePreciseAsFileTime`, which has
+    /// sub-microsecond precision but is slower than the clock used by `now`.
+    /// Elsewhere this is the same as `now`.
+    pub fn now_precise() -> FileTime {
+        cfg_if::cfg_if! {
+            if #[cfg(windows)] {
+                imp::now_precise().emulate_second_only_system()
+            } else {
+                FileTime::now()
+            }
+        }
     }
 
     /// Creates a new instance of `FileTime` with a number of seconds and
     /// nanoseconds relative to the Unix epoch, 1970-01-01T00:00:00Z.
     ///
     /// Negative seconds represent times before the Unix epoch, and positive
-    /// values represent times after it. Nanos always count forwards in time.
+    /// values represent times after it. Nanos always count forwards in time,
+    /// and any whole seconds in `nanos` are carried over into the seconds so
+    /// that the nanoseconds of a `FileTime` are always less than one billion.
     ///
     /// Note that this is typically the relative point that Unix time stamps are
     /// from, but on Windows the native time stamp is relative to January 1,
     /// 1601 so the return value of `seconds` from the returned `FileTime`
     /// instance may not be the same as that passed in.
+    ///
+    /// Windows also only stores time stamps with a precision of 100ns, so on
+    /// Windows `nanos` is truncated to a multiple of 100 to match what can
+    /// actually be written to disk.
     pub const fn from_unix_time(seconds: i64, nanos: u32) -> FileTime {
+        let seconds = seconds.saturating_add((nanos / 1_000_000_000) as i64);
+        let nanos = nanos % 1_000_000_000;
         FileTime {
             seconds: seconds + if cfg!(windows) { 11644473600 } else { 0 },
-            nanos,
+            nanos: if cfg!(windows) {
+                nanos / 100 * 100
+            } else {
+                nanos
+            },
         }
         .emulate_second_only_system()
     }
 
+    /// Creates a new timestamp from a whole number of seconds relative to the
+    /// Unix epoch start of January 1, 1970.
+    ///
+    /// This is a shortcut for `from_unix_time(unix_seconds, 0)`.
+    pub const fn from_secs(unix_seconds: i64) -> FileTime {
+        FileTime::from_unix_time(unix_seconds, 0)
+    }
+
+    /// Creates a new timestamp from an offset relative to an arbitrary epoch.
+    ///
+    /// `epoch_unix_seconds` is the epoch's own position as a number of seconds
+    /// since the Unix epoch, and `offset_seconds` and `nanos` are the time
+    /// relative to it. Some commonly used epochs are:
+    ///
+    /// * NTP, 1900-01-01T00:00:00Z: `-2_208_988_800`
+    /// * Classic Mac OS and HFS, 1904-01-01T00:00:00Z: `-2_082_844_800`
+    /// * GPS, 1980-01-06T00:00:00Z: `315_964_800`
+    /// * J2000, 2000-01-01T12:00:00Z: `946_728_000`
+    ///
+    /// Note that some time scales, such as GPS, don't include leap seconds;
+    /// this performs no leap second adjustment.
+    pub const fn from_epoch_offset(
+        epoch_unix_seconds: i64,
+        offset_seconds: i64,
+        nanos: u32,
+    ) -> FileTime {
+        FileTime::from_unix_time(epoch_unix_seconds + offset_seconds, nanos)
+    }
+
+    /// Creates a new timestamp from a number of nanoseconds relative to the
+    /// Unix epoch, checking that it can be represented.
+    ///
+    /// This is intended for untrusted input, such as timestamps decoded from
+    /// a serialization format. An error of kind `io::ErrorKind::InvalidInput`
+    /// is returned if the seconds don't fit in a `FileTime`, or if the time is
+    /// outside of the range that this platform can store, as checked by
+    /// [`FileTime::validate_for_platform`].
+    pub fn try_from_nanos_since_1970(nanos: i128) -> io::Result<FileTime> {
+        let offset = if cfg!(windows) { 11644473600 } else { 0 };
+        let seconds = i64::try_from(nanos.div_euclid(1_000_000_000))
+            .ok()
+            .filter(|s| s.checked_add(offset).is_some())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "timestamp seconds overflow the FileTime representation",
+                )
+            })?;
+        let ft = FileTime::from_unix_time(seconds, nanos.rem_euclid(1_000_000_000) as u32);
+        ft.validate_for_platform()?;
+        Ok(ft)
+    }
+
+    /// Parses a UTC timestamp in the RFC 3339 format, such as
+    /// `2021-05-01T12:00:00Z` or `2021-05-01T12:00:00.123456789Z`.
+    ///
+    /// The fractional seconds may have up to nine digits. Only UTC times
+    /// ending in `Z` are accepted, and other offsets such as `+02:00` are
+    /// rejected rather than converted, as are leap seconds. An error of kind
+    /// `io::ErrorKind::InvalidInput` is returned for malformed input.
+    pub fn from_rfc3339(s: &str) -> io::Result<FileTime> {
+        parse_rfc3339(s.as_bytes()).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid RFC 3339 UTC timestamp: {:?}", s),
+            )
+        })
+    }
+
     /// Creates a new timestamp from the last modification time listed in the
     /// specified metadata.
     ///
     /// The returned value corresponds to the `mtime` field of `stat` on Unix
     /// platforms and the `ftLastWriteTime` field on Windows platforms.
-    pub fn from_last_modification_time(meta: &fs::Metadata) -> FileTime {
-        imp::from_last_modification_time(meta).emulate_second_only_system()
+    ///
+    /// This accepts any `RawTimes`, which is normally `fs::Metadata`. Some
+    /// buggy filesystems report nanoseconds outside of `0..1_000_000_000`,
+    /// which are clamped to that range.
+    pub fn from_last_modification_time(meta: &impl RawTimes) -> FileTime {
+        FileTime::from_raw_parts(meta.mtime_parts())
     }
 
     /// Creates a new timestamp from the last access time listed in the
@@ -140,8 +288,10 @@ impl FileTime {
     ///
     /// The returned value corresponds to the `atime` field of `stat` on Unix
     /// platforms and the `ftLastAccessTime` field on Windows platforms.
-    pub fn from_last_access_time(meta: &fs::Metadata) -> FileTime {
-        imp::from_last_access_time(meta).emulate_second_only_system()
+    ///
+    /// This accepts any `RawTimes`, which is normally `fs::Metadata`.
+    pub fn from_last_access_time(meta: &impl RawTimes) -> FileTime {
+        FileTime::from_raw_parts(meta.atime_parts())
     }
 
     /// Creates a new timestamp from the creation time listed in the specified
@@ -150,16 +300,31 @@ impl FileTime {
     /// The returned value corresponds to the `birthtime` field of `stat` on
     /// Unix platforms and the `ftCreationTime` field on Windows platforms. Note
     /// that not all Unix platforms have this field available and may return
-    /// `None` in some circumstances.
-    pub fn from_creation_time(meta: &fs::Metadata) -> Option<FileTime> {
-        imp::from_creation_time(meta).map(|x| x.emulate_second_only_system())
+    /// `None` in some circumstances. All Apple platforms, including tvOS,
+    /// watchOS and Mac Catalyst, report it.
+    ///
+    /// On Linux the creation time is read with `statx`, which requires glibc
+    /// and kernel 4.11 or later as well as a filesystem which records it, such
+    /// as ext4, btrfs or XFS. If any of these is missing this returns `None`.
+    ///
+    /// This accepts any `RawTimes`, which is normally `fs::Metadata`.
+    pub fn from_creation_time(meta: &impl RawTimes) -> Option<FileTime> {
+        meta.btime_parts().map(FileTime::from_raw_parts)
+    }
+
+    // Nanoseconds of a second or more are clamped rather than carried into
+    // the seconds, as they come from a buggy filesystem rather than a
+    // deliberately denormalized value.
+    fn from_raw_parts((seconds, nanos): (i64, u32)) -> FileTime {
+        FileTime::from_unix_time(seconds, nanos.min(999_999_999))
     }
 
     /// Creates a new timestamp from the given SystemTime.
     ///
     /// Windows counts file times since 1601-01-01T00:00:00Z, and cannot
     /// represent times before this, but it's possible to create a SystemTime
-    /// that does. This function will error if passed such a SystemTime.
+    /// that does. This function will error if passed such a SystemTime, and
+    /// `try_from_system_time` can be used to detect this up front.
     pub fn from_system_time(time: SystemTime) -> FileTime {
         let epoch = if cfg!(windows) {
             UNIX_EPOCH - Duration::from_secs(11644473600)
@@ -181,13 +346,115 @@ impl FileTime {
                 };
 
                 FileTime {
-                    seconds: -1 * until_epoch.as_secs() as i64 + sec_offset,
+                    seconds: -(until_epoch.as_secs() as i64) + sec_offset,
                     nanos,
                 }
             })
             .emulate_second_only_system()
     }
 
+    /// Creates a new timestamp from the given SystemTime, failing if it can't
+    /// be stored on this platform.
+    ///
+    /// On Windows times before 1601-01-01T00:00:00Z can't be represented by a
+    /// `FILETIME`, so an error of kind `io::ErrorKind::InvalidInput` is
+    /// returned for them, while on Unix such times are simply negative Unix
+    /// times. The same error is returned for times outside of the range of
+    /// `time_t` where that's only 32 bits.
+    pub fn try_from_system_time(time: SystemTime) -> io::Result<FileTime> {
+        let ft = FileTime::from_system_time(time);
+        imp::check_range(&ft)?;
+        Ok(ft)
+    }
+
+    /// Creates a new timestamp from a raw `timespec`.
+    ///
+    /// The seconds are stored as-is, without any epoch adjustment, as they're
+    /// assumed to already be in the platform's native representation.
+    /// Nanoseconds outside of `0..1_000_000_000`, such as `UTIME_OMIT`, are
+    /// carried into the seconds, so the result always has fewer than one
+    /// billion nanoseconds.
+    #[cfg(unix)]
+    #[allow(clippy::unnecessary_cast)] // `time_t` is only 32 bits on some platforms
+    pub fn from_timespec(ts: libc::timespec) -> FileTime {
+        FileTime::from_native_parts(ts.tv_sec as i64, ts.tv_nsec as i64)
+    }
+
+    /// Creates a new timestamp from a raw `timeval`.
+    ///
+    /// As with `from_timespec`, the seconds are stored as-is and microseconds
+    /// outside of `0..1_000_000` are carried into the seconds.
+    #[cfg(unix)]
+    #[allow(clippy::unnecessary_cast)] // `time_t` is only 32 bits on some platforms
+    pub fn from_timeval(tv: libc::timeval) -> FileTime {
+        FileTime::from_native_parts(tv.tv_sec as i64, tv.tv_usec as i64 * 1000)
+    }
+
+    #[cfg(unix)]
+    fn from_native_parts(seconds: i64, nanos: i64) -> FileTime {
+        FileTime {
+            seconds: seconds.saturating_add(nanos.div_euclid(1_000_000_000)),
+            nanos: nanos.rem_euclid(1_000_000_000) as u32,
+        }
+        .emulate_second_only_system()
+    }
+
+    /// Creates a new timestamp from the two halves of a raw Windows
+    /// `FILETIME`, the number of 100ns intervals since January 1, 1601.
+    ///
+    /// Unlike `from_unix_time` no epoch adjustment is made since the value is
+    /// already in the native representation.
+    #[cfg(windows)]
+    pub fn from_filetime_raw(low: u32, high: u32) -> FileTime {
+        imp::from_intervals(((high as u64) << 32) | low as u64).emulate_second_only_system()
+    }
+
+    /// Creates a new timestamp from the time elapsed since the Windows epoch,
+    /// January 1, 1601.
+    ///
+    /// As with `from_unix_time`, the nanoseconds are truncated to a multiple
+    /// of 100.
+    #[cfg(windows)]
+    pub fn from_duration_since_windows_epoch(duration: Duration) -> FileTime {
+        FileTime {
+            seconds: duration.as_secs() as i64,
+            nanos: duration.subsec_nanos() / 100 * 100,
+        }
+        .emulate_second_only_system()
+    }
+
+    /// Decodes a timestamp from the 12 byte format produced by `to_le_bytes`.
+    ///
+    /// Returns `None` if the bytes don't encode a valid timestamp, either
+    /// because the encoded nanoseconds are not less than one billion or
+    /// because the seconds can't be represented by a `FileTime` on this
+    /// platform, so corrupt or untrusted input can be rejected.
+    pub fn from_le_bytes(bytes: [u8; 12]) -> Option<FileTime> {
+        let mut seconds = [0; 8];
+        let mut nanos = [0; 4];
+        seconds.copy_from_slice(&bytes[..8]);
+        nanos.copy_from_slice(&bytes[8..]);
+        let seconds = i64::from_le_bytes(seconds);
+        let nanos = u32::from_le_bytes(nanos);
+        if nanos >= 1_000_000_000 {
+            return None;
+        }
+        seconds.checked_add(if cfg!(windows) { 11644473600 } else { 0 })?;
+        Some(FileTime::from_unix_time(seconds, nanos))
+    }
+
+    /// Creates a new timestamp from a Windows `SYSTEMTIME`, the broken-down
+    /// calendar representation of a UTC time used by many Windows APIs.
+    ///
+    /// An error is returned if the fields of `st` aren't a valid date and
+    /// time, as reported by `SystemTimeToFileTime`.
+    #[cfg(windows)]
+    pub fn from_windows_system_time(
+        st: &windows_sys::Win32::Foundation::SYSTEMTIME,
+    ) -> io::Result<FileTime> {
+        imp::from_system_time_struct(st).map(|x| x.emulate_second_only_system())
+    }
+
     /// Returns the whole number of seconds represented by this timestamp.
     ///
     /// Note that this value's meaning is **platform specific**. On Unix
@@ -206,510 +473,5085 @@ impl FileTime {
         self.seconds - if cfg!(windows) { 11644473600 } else { 0 }
     }
 
+    /// Returns the whole number of seconds relative to the Unix epoch, the
+    /// inverse of `from_secs`.
+    ///
+    /// This is the same as `unix_seconds`, and unlike `seconds` doesn't
+    /// depend on the platform.
+    pub const fn secs(&self) -> i64 {
+        self.unix_seconds()
+    }
+
     /// Returns the nanosecond precision of this timestamp.
     ///
     /// The returned value is always less than one billion and represents a
     /// portion of a second forward from the seconds returned by the `seconds`
     /// method.
+    ///
+    /// On Windows file times are stored in 100ns intervals, so the returned
+    /// value is always a multiple of 100 there.
     pub const fn nanoseconds(&self) -> u32 {
         self.nanos
     }
-}
 
-impl fmt::Display for FileTime {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}.{:09}s", self.seconds, self.nanos)
+    /// Returns the time elapsed from the Windows epoch, January 1, 1601, to
+    /// this timestamp.
+    ///
+    /// Returns `None` if this timestamp is before the Windows epoch.
+    #[cfg(windows)]
+    pub fn since_windows_epoch(&self) -> Option<Duration> {
+        if self.seconds < 0 {
+            return None;
+        }
+        Some(Duration::new(self.seconds as u64, self.nanos))
     }
-}
 
-impl From<SystemTime> for FileTime {
-    fn from(time: SystemTime) -> FileTime {
-        FileTime::from_system_time(time)
+    /// Encodes this timestamp in a portable 12 byte format.
+    ///
+    /// The first 8 bytes are the seconds relative to the Unix epoch as a
+    /// little-endian `i64`, followed by the nanoseconds as a little-endian
+    /// `u32`. Since the seconds are relative to the Unix epoch the encoding is
+    /// the same on all platforms.
+    pub fn to_le_bytes(&self) -> [u8; 12] {
+        let mut bytes = [0; 12];
+        bytes[..8].copy_from_slice(&self.unix_seconds().to_le_bytes());
+        bytes[8..].copy_from_slice(&self.nanos.to_le_bytes());
+        bytes
     }
-}
 
-/// Set the last access and modification times for a file on the filesystem.
-///
-/// This function will set the `atime` and `mtime` metadata fields for a file
-/// on the local filesystem, returning any error encountered.
-pub fn set_file_times<P>(p: P, atime: FileTime, mtime: FileTime) -> io::Result<()>
-where
-    P: AsRef<Path>,
-{
-    imp::set_file_times(p.as_ref(), atime, mtime)
-}
+    /// Breaks this timestamp down into a UTC calendar date and time.
+    ///
+    /// The returned tuple is `(year, month, day, hour, minute, second,
+    /// nanos)` in the proleptic Gregorian calendar, with months and days
+    /// counting from 1. Times before the Unix epoch are handled, and leap
+    /// seconds are not represented. The year is an `i64` since the range of
+    /// `FileTime` spans far more years than an `i32` holds.
+    pub fn to_utc_components(&self) -> (i64, u8, u8, u8, u8, u8, u32) {
+        let seconds = self.unix_seconds();
+        let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+        let seconds_of_day = seconds.rem_euclid(86_400);
+        (
+            year,
+            month,
+            day,
+            (seconds_of_day / 3600) as u8,
+            (seconds_of_day / 60 % 60) as u8,
+            (seconds_of_day % 60) as u8,
+            self.nanos,
+        )
+    }
 
-/// Set the last access and modification times for a file handle.
-///
-/// This function will either or both of  the `atime` and `mtime` metadata
-/// fields for a file handle , returning any error encountered. If `None` is
-/// specified then the time won't be updated. If `None` is specified for both
-/// options then no action is taken.
-pub fn set_file_handle_times(
-    f: &fs::File,
-    atime: Option<FileTime>,
-    mtime: Option<FileTime>,
-) -> io::Result<()> {
-    imp::set_file_handle_times(f, atime, mtime)
-}
+    /// Converts this timestamp to a Windows `SYSTEMTIME`.
+    ///
+    /// `SYSTEMTIME` only has millisecond precision, so any smaller part of the
+    /// timestamp is truncated. An error is returned if the timestamp is
+    /// outside the range `FileTimeToSystemTime` accepts.
+    #[cfg(windows)]
+    pub fn to_windows_system_time(&self) -> io::Result<windows_sys::Win32::Foundation::SYSTEMTIME> {
+        imp::to_system_time_struct(self)
+    }
 
-/// Set the last access and modification times for a file on the filesystem.
-/// This function does not follow symlink.
-///
-/// This function will set the `atime` and `mtime` metadata fields for a file
-/// on the local filesystem, returning any error encountered.
-pub fn set_symlink_file_times<P>(p: P, atime: FileTime, mtime: FileTime) -> io::Result<()>
-where
-    P: AsRef<Path>,
-{
-    imp::set_symlink_file_times(p.as_ref(), atime, mtime)
-}
+    /// Returns the seconds relative to the Unix epoch and the nanoseconds of
+    /// this timestamp, as stored in the `tv_sec` and `tv_nsec` fields of a
+    /// `timespec`.
+    ///
+    /// This is useful to build the arguments of other syscalls, along with
+    /// `UTIME_OMIT` and `UTIME_NOW` for times which shouldn't be set to a
+    /// specific value.
+    #[cfg(unix)]
+    pub fn unix_timespec_parts(&self) -> (i64, i64) {
+        (self.unix_seconds(), i64::from(self.nanos))
+    }
 
-/// Set the last modification time for a file on the filesystem.
-///
-/// This function will set the `mtime` metadata field for a file on the local
-/// filesystem, returning any error encountered.
-///
-/// # Platform support
-///
-/// Where supported this will attempt to issue just one syscall to update only
-/// the `mtime`, but where not supported this may issue one syscall to learn the
-/// existing `atime` so only the `mtime` can be configured.
-pub fn set_file_mtime<P>(p: P, mtime: FileTime) -> io::Result<()>
-where
-    P: AsRef<Path>,
-{
-    imp::set_file_mtime(p.as_ref(), mtime)
-}
+    /// Returns a copy of this timestamp with the nanosecond component replaced
+    /// by `nanos`.
+    ///
+    /// As with `from_unix_time`, `nanos` is truncated to a multiple of 100 on
+    /// Windows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `nanos` is not less than one billion.
+    pub fn with_nanos(self, nanos: u32) -> FileTime {
+        assert!(
+            nanos < 1_000_000_000,
+            "nanoseconds must be less than one billion"
+        );
+        FileTime::from_unix_time(self.unix_seconds(), nanos)
+    }
 
-/// Set the last access time for a file on the filesystem.
-///
-/// This function will set the `atime` metadata field for a file on the local
-/// filesystem, returning any error encountered.
-///
-/// # Platform support
-///
-/// Where supported this will attempt to issue just one syscall to update only
-/// the `atime`, but where not supported this may issue one syscall to learn the
-/// existing `mtime` so only the `atime` can be configured.
-pub fn set_file_atime<P>(p: P, atime: FileTime) -> io::Result<()>
-where
-    P: AsRef<Path>,
-{
-    imp::set_file_atime(p.as_ref(), atime)
-}
+    /// Returns a copy of this timestamp with the whole seconds replaced by
+    /// `unix_seconds`.
+    ///
+    /// Like `from_unix_time`, `unix_seconds` is relative to the Unix epoch, so
+    /// on Windows the platform offset is applied and the new value of
+    /// `seconds` will differ from what's passed in.
+    pub fn with_seconds(self, unix_seconds: i64) -> FileTime {
+        FileTime::from_unix_time(unix_seconds, self.nanos)
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::{
-        set_file_atime, set_file_handle_times, set_file_mtime, set_file_times,
-        set_symlink_file_times, FileTime,
-    };
-    use std::fs::{self, File};
-    use std::io;
-    use std::path::Path;
-    use std::time::{Duration, UNIX_EPOCH};
-    use tempfile::Builder;
+    /// Returns this timestamp shifted by `delta` whole seconds, which may be
+    /// negative, leaving the nanoseconds unchanged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the result can't be represented by a `FileTime`. See
+    /// `checked_add_seconds` for a variant which returns `None` instead.
+    pub fn add_seconds(self, delta: i64) -> FileTime {
+        self.checked_add_seconds(delta)
+            .expect("overflow when adding seconds to timestamp")
+    }
 
-    #[cfg(unix)]
-    fn make_symlink_file<P, Q>(src: P, dst: Q) -> io::Result<()>
-    where
-        P: AsRef<Path>,
-        Q: AsRef<Path>,
-    {
-        use std::os::unix::fs::symlink;
-        symlink(src, dst)
+    /// Returns this timestamp shifted by `delta` whole seconds, which may be
+    /// negative, leaving the nanoseconds unchanged.
+    ///
+    /// Returns `None` if the result can't be represented by a `FileTime`.
+    pub fn checked_add_seconds(self, delta: i64) -> Option<FileTime> {
+        Some(FileTime {
+            seconds: self.seconds.checked_add(delta)?,
+            nanos: self.nanos,
+        })
     }
 
-    #[cfg(windows)]
-    fn make_symlink_file<P, Q>(src: P, dst: Q) -> io::Result<()>
-    where
-        P: AsRef<Path>,
-        Q: AsRef<Path>,
-    {
-        use std::os::windows::fs::symlink_file;
-        symlink_file(src, dst)
+    /// Checks whether this timestamp can be written to disk on the current
+    /// platform.
+    ///
+    /// This validates that the value fits the domain of the underlying
+    /// syscalls, for example that the seconds fit in a `time_t` on Unix or
+    /// that the value isn't before 1601 on Windows, without touching the
+    /// filesystem. An error is returned describing why the value can't be
+    /// set.
+    pub fn validate_for_platform(&self) -> io::Result<()> {
+        if self.nanos >= 1_000_000_000 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "nanoseconds must be less than one billion",
+            ));
+        }
+        imp::check_range(self)
     }
 
-    #[cfg(unix)]
-    fn make_symlink_dir<P, Q>(src: P, dst: Q) -> io::Result<()>
-    where
-        P: AsRef<Path>,
-        Q: AsRef<Path>,
-    {
-        use std::os::unix::fs::symlink;
-        symlink(src, dst)
+    /// Returns this timestamp unchanged if it is well formed and can be
+    /// written on the current platform, and an error otherwise.
+    ///
+    /// This performs the same checks as `validate_for_platform`, rejecting
+    /// nanoseconds of a full second or more rather than carrying them into
+    /// the seconds. It's intended for strict callers, such as validators of
+    /// untrusted archive metadata, which would rather reject a malformed
+    /// timestamp outright than silently fix it.
+    pub fn try_normalize(self) -> io::Result<FileTime> {
+        self.validate_for_platform()?;
+        Ok(self)
     }
 
-    #[cfg(windows)]
-    fn make_symlink_dir<P, Q>(src: P, dst: Q) -> io::Result<()>
-    where
-        P: AsRef<Path>,
-        Q: AsRef<Path>,
-    {
-        use std::os::windows::fs::symlink_dir;
-        symlink_dir(src, dst)
+    /// Returns the signed number of nanoseconds from `other` to this
+    /// timestamp, which is negative if `other` is later.
+    ///
+    /// Both timestamps are compared relative to the Unix epoch so the result
+    /// is the same on all platforms, and it can't overflow for any
+    /// representable `FileTime`.
+    pub const fn nanos_diff(&self, other: &FileTime) -> i128 {
+        self.unix_nanos() - other.unix_nanos()
     }
 
-    #[test]
-    #[cfg(windows)]
-    fn from_unix_time_test() {
-        let time = FileTime::from_unix_time(10, 100_000_000);
-        assert_eq!(11644473610, time.seconds);
-        assert_eq!(100_000_000, time.nanos);
+    /// Returns the amount of time elapsed from `earlier` to this timestamp.
+    ///
+    /// Both timestamps are compared relative to the Unix epoch so the result
+    /// is the same on all platforms. Returns `None` if `earlier` is actually
+    /// later than this timestamp.
+    pub fn duration_since(&self, earlier: &FileTime) -> Option<Duration> {
+        let diff = self.unix_nanos() - earlier.unix_nanos();
+        if diff < 0 {
+            return None;
+        }
+        Some(Duration::new(
+            (diff / 1_000_000_000) as u64,
+            (diff % 1_000_000_000) as u32,
+        ))
+    }
 
-        let time = FileTime::from_unix_time(-10, 100_000_000);
-        assert_eq!(11644473590, time.seconds);
-        assert_eq!(100_000_000, time.nanos);
+    /// Returns the amount of time elapsed from `earlier` to this timestamp,
+    /// or `None` if `earlier` is later than this timestamp.
+    ///
+    /// This is the same as `duration_since`, named after
+    /// `SystemTime::checked_duration_since`.
+    pub fn checked_duration_since(&self, earlier: FileTime) -> Option<Duration> {
+        self.duration_since(&earlier)
+    }
 
-        let time = FileTime::from_unix_time(-12_000_000_000, 0);
-        assert_eq!(-355526400, time.seconds);
-        assert_eq!(0, time.nanos);
+    /// Returns the amount of time elapsed from `earlier` to this timestamp,
+    /// or zero if `earlier` is later than this timestamp.
+    pub fn saturating_duration_since(&self, earlier: FileTime) -> Duration {
+        self.duration_since(&earlier).unwrap_or_default()
     }
 
-    #[test]
-    #[cfg(not(windows))]
-    fn from_unix_time_test() {
-        let time = FileTime::from_unix_time(10, 100_000_000);
-        assert_eq!(10, time.seconds);
-        assert_eq!(100_000_000, time.nanos);
+    /// Returns whether this timestamp is later than the current system time.
+    ///
+    /// A file modification time in the future is usually a sign of clock skew
+    /// between machines, and breaks tools which rely on comparing times.
+    pub fn is_in_future(&self) -> bool {
+        self.is_after(&FileTime::now())
+    }
 
-        let time = FileTime::from_unix_time(-10, 100_000_000);
-        assert_eq!(-10, time.seconds);
-        assert_eq!(100_000_000, time.nanos);
+    /// Returns whether more than `age` has elapsed between this timestamp and
+    /// the current system time.
+    ///
+    /// This reads the system clock, and is `false` for timestamps in the
+    /// future.
+    pub fn older_than(&self, age: Duration) -> bool {
+        FileTime::now()
+            .duration_since(self)
+            .is_some_and(|elapsed| elapsed > age)
+    }
 
-        let time = FileTime::from_unix_time(-12_000_000_000, 0);
-        assert_eq!(-12_000_000_000, time.seconds);
-        assert_eq!(0, time.nanos);
+    /// Returns how far this timestamp is from the current system time.
+    ///
+    /// The `Ordering` is `Greater` if this timestamp is in the future, `Less`
+    /// if it's in the past and `Equal` if it's the current time, and the
+    /// `Duration` is the absolute difference.
+    pub fn skew_from_now(&self) -> (Ordering, Duration) {
+        let now = FileTime::now();
+        match self.duration_since(&now) {
+            Some(ahead) if ahead > Duration::ZERO => (Ordering::Greater, ahead),
+            Some(_) => (Ordering::Equal, Duration::ZERO),
+            None => (Ordering::Less, now.saturating_duration_since(*self)),
+        }
     }
 
-    #[test]
+    /// Compares two timestamps relative to the Unix epoch.
+    ///
+    /// Unlike the derived `Ord` implementation, which compares the raw
+    /// platform-relative seconds and nanoseconds, this always compares the
+    /// normalized values so the result doesn't depend on the epoch used by
+    /// the current platform.
+    pub fn cmp_normalized(&self, other: &FileTime) -> Ordering {
+        self.unix_nanos().cmp(&other.unix_nanos())
+    }
+
+    /// Returns whether this timestamp is earlier than `other`.
+    ///
+    /// Like `cmp_normalized`, this compares the values relative to the Unix
+    /// epoch rather than the platform-relative values used by `Ord`.
+    pub fn is_before(&self, other: &FileTime) -> bool {
+        self.cmp_normalized(other) == Ordering::Less
+    }
+
+    /// Returns whether this timestamp is later than `other`.
+    ///
+    /// Like `cmp_normalized`, this compares the values relative to the Unix
+    /// epoch rather than the platform-relative values used by `Ord`.
+    pub fn is_after(&self, other: &FileTime) -> bool {
+        self.cmp_normalized(other) == Ordering::Greater
+    }
+
+    /// Returns the latest of the given timestamps, or `None` if there are
+    /// none.
+    ///
+    /// Timestamps are compared with `cmp_normalized`, so this is a
+    /// replacement for folding with `cmp::max` starting from `zero`.
+    pub fn max_of<I>(times: I) -> Option<FileTime>
+    where
+        I: IntoIterator<Item = FileTime>,
+    {
+        times.into_iter().max_by(FileTime::cmp_normalized)
+    }
+
+    /// Returns the earliest of the given timestamps, or `None` if there are
+    /// none.
+    ///
+    /// Timestamps are compared with `cmp_normalized`.
+    pub fn min_of<I>(times: I) -> Option<FileTime>
+    where
+        I: IntoIterator<Item = FileTime>,
+    {
+        times.into_iter().min_by(FileTime::cmp_normalized)
+    }
+
+    /// Rounds this timestamp to the nearest multiple of `granularity` since
+    /// the Unix epoch.
+    ///
+    /// This is useful to compare timestamps from filesystems with different
+    /// precision, for example by rounding both to a 2 second grid as used by
+    /// FAT. Values exactly halfway between two multiples are rounded away
+    /// from zero. A zero `granularity` returns the timestamp unchanged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rounded value can't be represented by a `FileTime`.
+    pub fn rounded_to(self, granularity: Duration) -> FileTime {
+        let granularity = granularity.as_nanos() as i128;
+        if granularity == 0 {
+            return self;
+        }
+        let nanos = self.unix_nanos();
+        let remainder = nanos.rem_euclid(granularity);
+        let down = nanos - remainder;
+        let rounded = match (remainder * 2).cmp(&granularity) {
+            Ordering::Less => down,
+            Ordering::Equal if nanos < 0 => down,
+            _ => down + granularity,
+        };
+        FileTime::from_unix_nanos(rounded)
+    }
+
+    /// Returns the total number of nanoseconds relative to the Unix epoch.
+    ///
+    /// This can't overflow for any representable `FileTime`, so it's used as
+    /// the common domain for arithmetic and comparisons.
+    const fn unix_nanos(&self) -> i128 {
+        let seconds = self.seconds as i128 - if cfg!(windows) { 11644473600 } else { 0 };
+        seconds * 1_000_000_000 + self.nanos as i128
+    }
+
+    /// The inverse of `unix_nanos`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the seconds don't fit in an `i64`.
+    fn from_unix_nanos(nanos: i128) -> FileTime {
+        let seconds = i64::try_from(nanos.div_euclid(1_000_000_000))
+            .expect("overflow when converting timestamp");
+        FileTime::from_unix_time(seconds, nanos.rem_euclid(1_000_000_000) as u32)
+    }
+}
+
+impl fmt::Debug for FileTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Times before the epoch are shown as the sign and magnitude of the
+        // offset, so half a second before it is `-0.500000000`.
+        let nanos = self.unix_nanos();
+        let sign = if nanos < 0 { "-" } else { "" };
+        let magnitude = nanos.unsigned_abs();
+        f.debug_struct("FileTime")
+            .field(
+                "unix",
+                &format_args!(
+                    "{}{}.{:09}",
+                    sign,
+                    magnitude / 1_000_000_000,
+                    magnitude % 1_000_000_000
+                ),
+            )
+            .field("raw_seconds", &self.seconds)
+            .finish()
+    }
+}
+
+impl fmt::Display for FileTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{:09}s", self.seconds, self.nanos)
+    }
+}
+
+impl From<SystemTime> for FileTime {
+    fn from(time: SystemTime) -> FileTime {
+        FileTime::from_system_time(time)
+    }
+}
+
+/// Converts a timestamp to a `SystemTime`, failing with an error of kind
+/// `io::ErrorKind::InvalidInput` if the time can't be represented by a
+/// `SystemTime`, whose range depends on the platform.
+impl TryFrom<FileTime> for SystemTime {
+    type Error = io::Error;
+
+    fn try_from(time: FileTime) -> io::Result<SystemTime> {
+        let seconds = time.unix_seconds();
+        let offset = Duration::new(seconds.unsigned_abs(), 0);
+        let whole = if seconds < 0 {
+            UNIX_EPOCH.checked_sub(offset)
+        } else {
+            UNIX_EPOCH.checked_add(offset)
+        };
+        whole
+            .and_then(|t| t.checked_add(Duration::new(0, time.nanos)))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "timestamp can't be represented by a SystemTime",
+                )
+            })
+    }
+}
+
+/// Generates timestamps across the whole range whose Unix seconds can be
+/// represented, favoring `FileTime::zero()`, the latest representable time and
+/// times just before the Unix epoch. The nanoseconds are always less than one
+/// billion.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for FileTime {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<FileTime> {
+        let offset = if cfg!(windows) { 11644473600 } else { 0 };
+        Ok(match u.int_in_range(0..=7u8)? {
+            0 => FileTime::zero(),
+            1 => FileTime {
+                seconds: i64::MAX,
+                nanos: 999_999_999,
+            },
+            2 => FileTime::from_unix_time(-1, u.int_in_range(0..=999_999_999)?),
+            _ => FileTime {
+                seconds: u.int_in_range(i64::MIN + offset..=i64::MAX)?,
+                nanos: u.int_in_range(0..=999_999_999)?,
+            },
+        })
+    }
+}
+
+/// Returns a `proptest` strategy generating timestamps.
+///
+/// This covers times near the present, times before the Unix epoch, and the
+/// boundaries of the range whose Unix seconds can be represented, including
+/// `FileTime::zero()`. The nanoseconds are always less than one billion.
+#[cfg(feature = "proptest")]
+pub fn filetime_strategy() -> impl proptest::strategy::Strategy<Value = FileTime> {
+    use proptest::prelude::*;
+
+    let offset = if cfg!(windows) { 11644473600 } else { 0 };
+    let nanos = 0..1_000_000_000u32;
+    prop_oneof![
+        Just(FileTime::zero()),
+        Just(FileTime {
+            seconds: i64::MAX,
+            nanos: 999_999_999,
+        }),
+        Just(FileTime {
+            seconds: i64::MIN + offset,
+            nanos: 0,
+        }),
+        (0..4_102_444_800i64, nanos.clone()).prop_map(|(s, n)| FileTime::from_unix_time(s, n)),
+        (-4_102_444_800..0i64, nanos.clone()).prop_map(|(s, n)| FileTime::from_unix_time(s, n)),
+        (i64::MIN + offset..=i64::MAX, nanos)
+            .prop_map(|(seconds, nanos)| FileTime { seconds, nanos }),
+    ]
+}
+
+// Some buggy filesystems report nanoseconds outside of `0..1_000_000_000`,
+// which are clamped rather than stored as-is.
+#[cfg(unix)]
+fn clamp_nsec(nsec: i64) -> u32 {
+    nsec.clamp(0, 999_999_999) as u32
+}
+
+fn parse_rfc3339(s: &[u8]) -> Option<FileTime> {
+    fn digits(s: &[u8]) -> Option<u32> {
+        if s.is_empty() || !s.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        Some(s.iter().fold(0, |n, d| n * 10 + u32::from(d - b'0')))
+    }
+
+    if s.len() < 20 || s[4] != b'-' || s[7] != b'-' || s[13] != b':' || s[16] != b':' {
+        return None;
+    }
+    if !matches!(s[10], b'T' | b't') || !matches!(s[s.len() - 1], b'Z' | b'z') {
+        return None;
+    }
+    let year = digits(&s[0..4])?;
+    let month = digits(&s[5..7])?;
+    let day = digits(&s[8..10])?;
+    let hour = digits(&s[11..13])?;
+    let minute = digits(&s[14..16])?;
+    let second = digits(&s[17..19])?;
+    let nanos = match &s[19..s.len() - 1] {
+        [] => 0,
+        [b'.', fraction @ ..] if fraction.len() <= 9 => {
+            digits(fraction)? * 10u32.pow(9 - fraction.len() as u32)
+        }
+        _ => return None,
+    };
+
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return None,
+    };
+    if day == 0 || day > days_in_month || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+
+    let days = days_from_civil(i64::from(year), month, day);
+    let seconds = days * 86_400 + i64::from(hour * 3_600 + minute * 60 + second);
+    Some(FileTime::from_unix_time(seconds, nanos))
+}
+
+// The number of days from 1970-01-01 to the given date in the proleptic
+// Gregorian calendar, from http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * i64::from((month + 9) % 12) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+// The inverse of `days_from_civil`, returning the year, month and day.
+fn civil_from_days(days: i64) -> (i64, u8, u8) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u8;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+/// The source of the raw times read by `FileTime`'s extractors, such as
+/// `FileTime::from_last_modification_time`.
+///
+/// This is implemented for `fs::Metadata`, and can be implemented for other
+/// types to test code which works with file times without touching the
+/// filesystem. Each time is returned as whole seconds relative to the Unix
+/// epoch and nanoseconds, as with `FileTime::from_unix_time`.
+pub trait RawTimes {
+    /// Returns the last modification time.
+    fn mtime_parts(&self) -> (i64, u32);
+
+    /// Returns the last access time.
+    fn atime_parts(&self) -> (i64, u32);
+
+    /// Returns the creation time, or `None` if it isn't available.
+    fn btime_parts(&self) -> Option<(i64, u32)>;
+}
+
+impl RawTimes for fs::Metadata {
+    fn mtime_parts(&self) -> (i64, u32) {
+        let time = imp::from_last_modification_time(self);
+        (time.unix_seconds(), time.nanos)
+    }
+
+    fn atime_parts(&self) -> (i64, u32) {
+        let time = imp::from_last_access_time(self);
+        (time.unix_seconds(), time.nanos)
+    }
+
+    fn btime_parts(&self) -> Option<(i64, u32)> {
+        imp::from_creation_time(self).map(|time| (time.unix_seconds(), time.nanos))
+    }
+}
+
+/// A timestamp for a file normalized relative to the Unix epoch.
+///
+/// Unlike `FileTime`, whose value is relative to the current platform's
+/// native epoch, this always stores seconds and nanoseconds relative to
+/// 1970-01-01T00:00:00Z. The `Ord`, `Hash` and `Display` implementations are
+/// therefore the same on all platforms, which makes this the type to use when
+/// persisting or transmitting timestamps.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Debug, Copy, Clone, Hash)]
+pub struct UnixFileTime {
+    seconds: i64,
+    nanos: u32,
+}
+
+impl UnixFileTime {
+    /// Creates a new timestamp with a number of seconds and nanoseconds
+    /// relative to the Unix epoch.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `nanos` is not less than one billion.
+    pub const fn new(seconds: i64, nanos: u32) -> UnixFileTime {
+        assert!(
+            nanos < 1_000_000_000,
+            "nanoseconds must be less than one billion"
+        );
+        UnixFileTime { seconds, nanos }
+    }
+
+    /// Returns the whole number of seconds relative to the Unix epoch.
+    pub const fn seconds(&self) -> i64 {
+        self.seconds
+    }
+
+    /// Returns the nanosecond precision of this timestamp.
+    pub const fn nanoseconds(&self) -> u32 {
+        self.nanos
+    }
+}
+
+impl fmt::Display for UnixFileTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{:09}s", self.seconds, self.nanos)
+    }
+}
+
+impl From<FileTime> for UnixFileTime {
+    fn from(time: FileTime) -> UnixFileTime {
+        UnixFileTime {
+            seconds: time.unix_seconds(),
+            nanos: time.nanos,
+        }
+    }
+}
+
+impl From<UnixFileTime> for FileTime {
+    fn from(time: UnixFileTime) -> FileTime {
+        FileTime::from_unix_time(time.seconds, time.nanos)
+    }
+}
+
+/// The modification time of a file in a fixed layout, suitable for storing
+/// directly in a cache index.
+///
+/// The fields are in native endianness, so the raw bytes of this structure
+/// aren't portable between machines. Use `UnixFileTime` or the individual
+/// fields when the value needs to be persisted portably.
+#[repr(C)]
+#[derive(Eq, PartialEq, Debug, Copy, Clone, Hash)]
+pub struct PackedTimes {
+    /// Whole seconds of the modification time relative to the Unix epoch.
+    pub mtime_secs: i64,
+    /// Nanoseconds of the modification time, always less than one billion.
+    pub mtime_nanos: u32,
+}
+
+/// Extracts the modification time from `meta` as a `PackedTimes`.
+///
+/// This is equivalent to `FileTime::from_last_modification_time` but
+/// produces a compact value with a stable layout.
+pub fn packed_mtime(meta: &fs::Metadata) -> PackedTimes {
+    let mtime = FileTime::from_last_modification_time(meta);
+    PackedTimes {
+        mtime_secs: mtime.unix_seconds(),
+        mtime_nanos: mtime.nanoseconds(),
+    }
+}
+
+/// Get the last access, modification and creation times for a file on the
+/// filesystem.
+///
+/// This follows symlinks, reading the times of the file they point to. The
+/// creation time is `None` where it isn't available, as with
+/// `FileTime::from_creation_time`.
+pub fn get_file_times<P>(p: P) -> io::Result<(FileTime, FileTime, Option<FileTime>)>
+where
+    P: AsRef<Path>,
+{
+    Ok(times_from_metadata(&fs::metadata(p)?))
+}
+
+/// Get the last access, modification and creation times for a file on the
+/// filesystem. This function does not follow symlink.
+///
+/// The creation time is `None` where it isn't available, as with
+/// `FileTime::from_creation_time`.
+pub fn get_symlink_file_times<P>(p: P) -> io::Result<(FileTime, FileTime, Option<FileTime>)>
+where
+    P: AsRef<Path>,
+{
+    Ok(times_from_metadata(&fs::symlink_metadata(p)?))
+}
+
+/// Get the last access, modification and creation times for an open file.
+///
+/// The times are read with a single `fstat` on Unix, or
+/// `GetFileInformationByHandle` on Windows, so they're consistent with each
+/// other and refer to the file behind the handle even if its path has since
+/// changed. The creation time is `None` where it isn't available, as with
+/// `FileTime::from_creation_time`.
+pub fn handle_times(f: &fs::File) -> io::Result<(FileTime, FileTime, Option<FileTime>)> {
+    Ok(times_from_metadata(&f.metadata()?))
+}
+
+fn times_from_metadata(meta: &fs::Metadata) -> (FileTime, FileTime, Option<FileTime>) {
+    (
+        FileTime::from_last_access_time(meta),
+        FileTime::from_last_modification_time(meta),
+        FileTime::from_creation_time(meta),
+    )
+}
+
+/// Where a `TaggedTime` came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimeSource {
+    /// The last modification time of a file.
+    Modified,
+    /// The last access time of a file.
+    Accessed,
+    /// The creation time of a file.
+    Created,
+    /// A time which wasn't read from file metadata at all.
+    Constructed,
+}
+
+/// A timestamp along with the metadata field it was read from.
+///
+/// This is intended as a diagnostic aid, for example to include in verbose
+/// logging which time a value actually is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TaggedTime {
+    /// The timestamp itself.
+    pub time: FileTime,
+    /// Where `time` came from.
+    pub source: TimeSource,
+}
+
+impl From<FileTime> for TaggedTime {
+    fn from(time: FileTime) -> TaggedTime {
+        TaggedTime {
+            time,
+            source: TimeSource::Constructed,
+        }
+    }
+}
+
+/// The times of a file, each tagged with the field it was read from, as
+/// returned by `from_metadata_tagged`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TaggedTimes {
+    /// The last access time.
+    pub atime: TaggedTime,
+    /// The last modification time.
+    pub mtime: TaggedTime,
+    /// The creation time, where available.
+    pub btime: Option<TaggedTime>,
+}
+
+/// Reads the access, modification and creation times from `meta`, tagging
+/// each with the field it came from.
+///
+/// The times are the same as those returned by `FileTime`'s extractors such
+/// as `FileTime::from_last_modification_time`.
+pub fn from_metadata_tagged(meta: &fs::Metadata) -> TaggedTimes {
+    let (atime, mtime, btime) = times_from_metadata(meta);
+    let tag = |time, source| TaggedTime { time, source };
+    TaggedTimes {
+        atime: tag(atime, TimeSource::Accessed),
+        mtime: tag(mtime, TimeSource::Modified),
+        btime: btime.map(|time| tag(time, TimeSource::Created)),
+    }
+}
+
+/// Returns the newest modification time of anything within the directory
+/// `root`, or `None` if it's empty.
+///
+/// The whole tree below `root` is walked, including the modification times of
+/// subdirectories but not of `root` itself. Symlinks aren't followed, so their
+/// own modification time is used, which avoids cycles and counting the same
+/// file twice.
+pub fn newest_mtime<P: AsRef<Path>>(root: P) -> io::Result<Option<FileTime>> {
+    let mut newest: Option<FileTime> = None;
+    let mut dirs = vec![root.as_ref().to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let meta = fs::symlink_metadata(entry.path())?;
+            let mtime = FileTime::from_last_modification_time(&meta);
+            newest = Some(newest.map_or(mtime, |newest| newest.max(mtime)));
+            if meta.is_dir() {
+                dirs.push(entry.path());
+            }
+        }
+    }
+    Ok(newest)
+}
+
+/// Get the last modification time for a file on the filesystem.
+///
+/// This follows symlinks and is a shorthand for calling
+/// `FileTime::from_last_modification_time` on the file's metadata.
+pub fn mtime_of<P>(p: P) -> io::Result<FileTime>
+where
+    P: AsRef<Path>,
+{
+    Ok(FileTime::from_last_modification_time(&fs::metadata(p)?))
+}
+
+/// Get the last access time for a file on the filesystem.
+///
+/// This follows symlinks and is a shorthand for calling
+/// `FileTime::from_last_access_time` on the file's metadata.
+pub fn atime_of<P>(p: P) -> io::Result<FileTime>
+where
+    P: AsRef<Path>,
+{
+    Ok(FileTime::from_last_access_time(&fs::metadata(p)?))
+}
+
+/// Get the creation time for a file on the filesystem, if available.
+///
+/// This follows symlinks and is a shorthand for calling
+/// `FileTime::from_creation_time` on the file's metadata.
+pub fn btime_of<P>(p: P) -> io::Result<Option<FileTime>>
+where
+    P: AsRef<Path>,
+{
+    Ok(FileTime::from_creation_time(&fs::metadata(p)?))
+}
+
+/// Returns the earliest time known to be associated with a file.
+///
+/// This is intended as a best guess at when a file was created which is never
+/// later than when it really was. The time is chosen as follows:
+///
+/// 1. The creation time, if the platform and filesystem report one.
+/// 2. Otherwise the earlier of the status change time (`ctime`, on Unix only)
+///    and the modification time. The status change time is bumped by any
+///    change to the file's metadata, while the modification time can be set
+///    to an arbitrary value, so neither alone is a lower bound.
+pub fn earliest_known_time(meta: &fs::Metadata) -> FileTime {
+    earliest_of(
+        FileTime::from_creation_time(meta),
+        imp::from_status_change_time(meta).map(|x| x.emulate_second_only_system()),
+        FileTime::from_last_modification_time(meta),
+    )
+}
+
+fn earliest_of(btime: Option<FileTime>, ctime: Option<FileTime>, mtime: FileTime) -> FileTime {
+    if let Some(btime) = btime {
+        return btime;
+    }
+    match ctime {
+        Some(ctime) if ctime.is_before(&mtime) => ctime,
+        _ => mtime,
+    }
+}
+
+/// Returns whether times are set with full nanosecond precision on the
+/// current system.
+///
+/// This is `false` where only less precise interfaces are available, such as
+/// `utimes` with microsecond precision, and on Windows, which stores times in
+/// 100ns intervals. On Linux this is `true` until `utimensat` has been found
+/// to be unsupported by the running kernel, which is only detected once times
+/// have been set, and always `false` with the `force-utimes` feature enabled.
+/// FreeBSD, NetBSD and OpenBSD similarly fall back to `utimes` if their
+/// kernel lacks `utimensat`. On macOS it depends on whether the OS provides
+/// `utimensat`, which was added in macOS 10.13.
+pub fn kernel_supports_nanosecond_times() -> bool {
+    imp::supports_nanosecond_times()
+}
+
+/// Returns whether creation times can be read on the current platform.
+///
+/// When this returns `false`, `FileTime::from_creation_time` always returns
+/// `None`. When it returns `true` the creation time may still be unavailable
+/// for some files, for example on Linux where it depends on the kernel and
+/// filesystem supporting `statx` birth times.
+pub const fn supports_creation_time() -> bool {
+    imp::SUPPORTS_CREATION_TIME
+}
+
+/// Returns whether this crate can write creation times on the current
+/// platform.
+///
+/// This is only the case on Windows, where `SetFileTime` accepts a creation
+/// time alongside the access and modification times.
+pub const fn supports_setting_creation_time() -> bool {
+    cfg!(windows)
+}
+
+/// Returns whether this crate can write status change times (`ctime`) on the
+/// current platform, which is never the case.
+///
+/// Unix kernels deliberately don't let userspace choose the status change
+/// time: it's set to the current time by any change to a file's metadata,
+/// including setting its other times with this crate. The only way around
+/// this is to modify the filesystem directly, for example with `debugfs` on
+/// an unmounted ext4 filesystem, which is out of scope for this crate.
+pub const fn can_set_ctime() -> bool {
+    false
+}
+
+/// Set the last access and modification times for a file on the filesystem.
+///
+/// This function will set the `atime` and `mtime` metadata fields for a file
+/// on the local filesystem, returning any error encountered.
+///
+/// Since both times are given the existing times are never read first, unlike
+/// `set_file_mtime` and `set_file_atime`. Both are written by a single call:
+/// `utimensat` (or `utimes` where that's unavailable) on Unix, and
+/// `SetFileTime` on Windows and `futimens` on Redox after opening the file.
+/// This is guaranteed, so callers can rely on it. The only extra calls are a
+/// retry with `utimes` the first time a kernel reports that `utimensat` isn't
+/// implemented, after which `utimes` is used directly, and on Linux a retry
+/// by path when a `/proc/self/fd/N` path names a descriptor which can't be
+/// used directly.
+///
+/// On Unix the file is never opened, so this can be used on FIFOs, sockets
+/// and device nodes without blocking or triggering any side effects of
+/// opening them. On Windows the file is opened with
+/// `FILE_FLAG_BACKUP_SEMANTICS`, and on Redox it's opened by path; prefer
+/// `set_file_handle_times` there if the file can't be opened without
+/// blocking. Paths longer than `MAX_PATH` work on Windows without needing a
+/// `\\?\` prefix, as the standard library adds it when opening the file.
+///
+/// Times belong to the file itself rather than to the name used to reach
+/// it, so if the file has multiple hard links the change is visible through
+/// all of them. This is the case both for Unix inodes and for NTFS, where the
+/// times are stored in the file's record shared by all of its names.
+///
+/// Callers which already have an open file should use
+/// `set_file_handle_times` rather than building a `/proc/self/fd/N` or
+/// `/dev/fd/N` path to it, as resolving those magic links doesn't always
+/// reach the open file, for example once it's been unlinked. On Linux such
+/// paths are detected and the times are set through the descriptor they name
+/// where possible.
+pub fn set_file_times<P>(p: P, atime: FileTime, mtime: FileTime) -> io::Result<()>
+where
+    P: AsRef<Path>,
+{
+    imp::set_file_times(p.as_ref(), atime, mtime)
+}
+
+/// Set the last access and modification times for a file which must already
+/// exist, like `touch -c`.
+///
+/// This is the same as `set_file_times`, which never creates files, but
+/// makes that choice clear at the call site. An error of kind
+/// `io::ErrorKind::NotFound` is returned if there's no file at `p`.
+pub fn touch_existing<P>(p: P, atime: FileTime, mtime: FileTime) -> io::Result<()>
+where
+    P: AsRef<Path>,
+{
+    imp::set_file_times(p.as_ref(), atime, mtime)
+}
+
+/// Set the last access and modification times for a file, creating it as an
+/// empty file first if it doesn't exist, like `touch`.
+///
+/// An existing file is never opened or truncated, so its contents are left
+/// alone. Creating the file needs write access to its directory, and the
+/// parent directory isn't created if it's missing.
+pub fn touch_or_create<P>(p: P, atime: FileTime, mtime: FileTime) -> io::Result<()>
+where
+    P: AsRef<Path>,
+{
+    let p = p.as_ref();
+    match fs::OpenOptions::new().write(true).create_new(true).open(p) {
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(e),
+    }
+    imp::set_file_times(p, atime, mtime)
+}
+
+/// Set the last access and modification times for a file on the filesystem,
+/// retrying if the file is temporarily in use.
+///
+/// On Windows, antivirus and indexing services can briefly hold a file open
+/// without sharing it, causing `set_file_times` to fail with
+/// `ERROR_SHARING_VIOLATION`. This retries such failures up to `retries`
+/// times, sleeping for `delay` before the first retry and doubling the delay
+/// before each subsequent one. Any other error is returned immediately. On
+/// other platforms this is equivalent to `set_file_times`.
+pub fn set_file_times_with_retry<P>(
+    p: P,
+    atime: FileTime,
+    mtime: FileTime,
+    retries: u32,
+    delay: Duration,
+) -> io::Result<()>
+where
+    P: AsRef<Path>,
+{
+    cfg_if::cfg_if! {
+        if #[cfg(windows)] {
+            imp::set_file_times_with_retry(p.as_ref(), atime, mtime, retries, delay)
+        } else {
+            let _ = (retries, delay);
+            imp::set_file_times(p.as_ref(), atime, mtime)
+        }
+    }
+}
+
+/// Set the last access and modification times for a file on the filesystem
+/// while explicitly preserving its creation time.
+///
+/// On Windows this reads the file's current creation time and passes it back
+/// in to the same `SetFileTime` call that sets the `atime` and `mtime`, so no
+/// driver or other side effect of the update can alter it. On other platforms
+/// this is equivalent to `set_file_times`.
+pub fn set_file_times_preserve_crtime<P>(p: P, atime: FileTime, mtime: FileTime) -> io::Result<()>
+where
+    P: AsRef<Path>,
+{
+    cfg_if::cfg_if! {
+        if #[cfg(windows)] {
+            imp::set_file_times_preserve_crtime(p.as_ref(), atime, mtime)
+        } else {
+            imp::set_file_times(p.as_ref(), atime, mtime)
+        }
+    }
+}
+
+/// Set the last access and modification times for a file on the filesystem,
+/// opening it with POSIX semantics.
+///
+/// This is the same as `set_file_times`, except that the file is opened with
+/// `FILE_FLAG_POSIX_SEMANTICS` in addition to the usual
+/// `FILE_FLAG_BACKUP_SEMANTICS`. That makes the lookup of `p` case sensitive,
+/// so in a directory with case sensitivity enabled only the file whose name
+/// matches exactly is stamped, as it would be on Unix. Case sensitive lookups
+/// also depend on the system allowing them, and where it doesn't the flag has
+/// no effect. Backup semantics are still requested so directories can be
+/// opened as well.
+#[cfg(windows)]
+pub fn set_file_times_posix_semantics<P>(p: P, atime: FileTime, mtime: FileTime) -> io::Result<()>
+where
+    P: AsRef<Path>,
+{
+    imp::set_file_times_posix_semantics(p.as_ref(), atime, mtime)
+}
+
+/// Set the last access and modification times for a file on the filesystem,
+/// given its path as a C string.
+///
+/// This is the same as `set_file_times`, but avoids allocating a new
+/// null-terminated copy of the path for callers that already have one. As a
+/// `CStr` the path is already guaranteed not to contain any interior NUL
+/// bytes.
+#[cfg(unix)]
+pub fn set_file_times_cstr(p: &std::ffi::CStr, atime: FileTime, mtime: FileTime) -> io::Result<()> {
+    imp::set_file_times_cstr(p, atime, mtime)
+}
+
+/// Set the last access and modification times for a file on the filesystem
+/// through an `O_PATH` descriptor.
+///
+/// On Linux the file is opened with `O_PATH`, which doesn't require read or
+/// write permission on the file itself, and the times are set with
+/// `utimensat` and `AT_EMPTY_PATH`. This needs Linux 5.8 or later. On other
+/// platforms, and with the `force-utimes` feature enabled, this is equivalent
+/// to `set_file_times`.
+pub fn set_file_times_opath<P>(p: P, atime: FileTime, mtime: FileTime) -> io::Result<()>
+where
+    P: AsRef<Path>,
+{
+    cfg_if::cfg_if! {
+        if #[cfg(target_os = "linux")] {
+            imp::set_file_times_opath(p.as_ref(), atime, mtime)
+        } else {
+            imp::set_file_times(p.as_ref(), atime, mtime)
+        }
+    }
+}
+
+/// A handle to a directory for setting the times of files by their path
+/// relative to it.
+///
+/// Holding the directory open means that its entries can be updated even if
+/// the directory itself is later moved or replaced, which is useful when
+/// extracting an archive into a sandbox directory. Times are set with
+/// `utimensat`, so note that `..` components and symlinks within the
+/// relative path are still resolved by the kernel and may lead outside of
+/// the directory; they should be rejected by the caller if that's a concern.
+///
+/// With the `force-utimes` feature on Linux, times are instead set with
+/// `utimes` through the directory's entry in `/proc/self/fd`, so this
+/// requires `/proc` to be mounted.
+#[cfg(all(unix, not(target_os = "redox")))]
+#[derive(Debug)]
+pub struct DirStamp {
+    dirfd: std::os::unix::io::OwnedFd,
+}
+
+#[cfg(all(unix, not(target_os = "redox")))]
+impl DirStamp {
+    /// Opens the directory at `dir`.
+    pub fn open<P>(dir: P) -> io::Result<DirStamp>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(DirStamp {
+            dirfd: fs::File::open(dir)?.into(),
+        })
+    }
+
+    /// Set the last access and modification times for the file at `rel`,
+    /// relative to this directory.
+    ///
+    /// If `follow` is `false` and `rel` is a symlink then the times of the
+    /// symlink itself are set, like `set_symlink_file_times`.
+    pub fn stamp(
+        &self,
+        rel: &Path,
+        atime: FileTime,
+        mtime: FileTime,
+        follow: bool,
+    ) -> io::Result<()> {
+        use std::ffi::CString;
+        use std::os::unix::prelude::*;
+
+        let rel = CString::new(rel.as_os_str().as_bytes())?;
+        imp::set_file_times_at(self.dirfd.as_raw_fd(), &rel, atime, mtime, !follow)
+    }
+}
+
+#[cfg(all(unix, not(target_os = "redox")))]
+impl From<std::os::unix::io::OwnedFd> for DirStamp {
+    fn from(dirfd: std::os::unix::io::OwnedFd) -> DirStamp {
+        DirStamp { dirfd }
+    }
+}
+
+/// Set the last access and modification times for a file handle.
+///
+/// This function will either or both of  the `atime` and `mtime` metadata
+/// fields for a file handle , returning any error encountered. If `None` is
+/// specified then the time won't be updated. If `None` is specified for both
+/// options then no action is taken.
+///
+/// # Platform support
+///
+/// On Windows the handle must have been opened with `FILE_WRITE_ATTRIBUTES`
+/// access, which is included when opening a file for writing. A handle
+/// opened read-only results in an error saying so; such a handle can first be
+/// upgraded with `reopen_with_write_attributes`.
+///
+/// Getting a handle to a device node means opening the device, which can
+/// have side effects such as rewinding a tape drive. Use `set_file_times` to
+/// stamp device nodes instead, which on Unix never opens the file.
+pub fn set_file_handle_times(
+    f: &fs::File,
+    atime: Option<FileTime>,
+    mtime: Option<FileTime>,
+) -> io::Result<()> {
+    imp::set_file_handle_times(f, atime, mtime)
+}
+
+/// Sets the times of a file handle once writing to it has finished.
+///
+/// This is intended for code which extracts files from a stream, such as an
+/// archive, so that each file's times are set as soon as its contents have
+/// been written. The times are applied with `set_file_handle_times`, and so
+/// have the same requirements of the handle.
+///
+/// Call `finish` to apply the times and find out whether that succeeded. If
+/// the value is instead dropped, the times are applied but any error is
+/// ignored.
+#[derive(Debug)]
+pub struct StampOnClose<'a> {
+    file: &'a fs::File,
+    atime: Option<FileTime>,
+    mtime: Option<FileTime>,
+}
+
+impl<'a> StampOnClose<'a> {
+    /// Creates a new value which sets the times of `file` when finished. If
+    /// `None` is specified then that time won't be updated.
+    pub fn new(
+        file: &'a fs::File,
+        atime: Option<FileTime>,
+        mtime: Option<FileTime>,
+    ) -> StampOnClose<'a> {
+        StampOnClose { file, atime, mtime }
+    }
+
+    /// Applies the times to the file, returning any error encountered.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.apply()
+    }
+
+    fn apply(&mut self) -> io::Result<()> {
+        let (atime, mtime) = (self.atime.take(), self.mtime.take());
+        if atime.is_none() && mtime.is_none() {
+            return Ok(());
+        }
+        set_file_handle_times(self.file, atime, mtime)
+    }
+}
+
+impl Drop for StampOnClose<'_> {
+    fn drop(&mut self) {
+        let _ = self.apply();
+    }
+}
+
+/// Opens a new handle to the same file as `f` with the access required to
+/// set its times.
+///
+/// This uses `ReOpenFile` to request `FILE_WRITE_ATTRIBUTES` access, so that
+/// a file which was opened read-only can be passed to
+/// `set_file_handle_times` without having to open it again by path.
+#[cfg(windows)]
+pub fn reopen_with_write_attributes(f: &fs::File) -> io::Result<fs::File> {
+    imp::reopen_with_write_attributes(f)
+}
+
+/// Set the last access and modification times for a file on the filesystem.
+/// This function does not follow symlink.
+///
+/// This function will set the `atime` and `mtime` metadata fields for a file
+/// on the local filesystem, returning any error encountered.
+///
+/// # Platform support
+///
+/// Emscripten's filesystem doesn't support updating the times of a symlink
+/// itself, so there this always returns an error of kind
+/// `io::ErrorKind::Unsupported`.
+pub fn set_symlink_file_times<P>(p: P, atime: FileTime, mtime: FileTime) -> io::Result<()>
+where
+    P: AsRef<Path>,
+{
+    imp::set_symlink_file_times(p.as_ref(), atime, mtime)
+}
+
+/// Whether a function that sets file times follows a symlink at the end of
+/// the path, for use with [`set_times_with_flags`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SymlinkFlag {
+    /// Set the times of the file that the symlink points to, as
+    /// [`set_file_times`] does.
+    Follow,
+    /// Set the times of the symlink itself, as [`set_symlink_file_times`]
+    /// does.
+    NoFollow,
+}
+
+/// Set the last access and modification times for a file on the filesystem,
+/// with an explicit choice of whether a final symlink is followed.
+///
+/// This is equivalent to [`set_file_times`] for `SymlinkFlag::Follow` and
+/// to [`set_symlink_file_times`] for `SymlinkFlag::NoFollow`, but makes the
+/// behavior clear at the call site.
+pub fn set_times_with_flags<P>(
+    p: P,
+    atime: FileTime,
+    mtime: FileTime,
+    flags: SymlinkFlag,
+) -> io::Result<()>
+where
+    P: AsRef<Path>,
+{
+    let p = p.as_ref();
+    match flags {
+        SymlinkFlag::Follow => set_file_times(p, atime, mtime),
+        SymlinkFlag::NoFollow => set_symlink_file_times(p, atime, mtime),
+    }
+}
+
+/// A selection of times to set on a file, built up with its methods and then
+/// applied with `apply`.
+///
+/// Any time which isn't selected is left unchanged. This consolidates the
+/// combinations of the other setters, such as setting only one time, setting
+/// a time to the current time or setting the creation time, behind a single
+/// type.
+///
+/// ```no_run
+/// use filetime::{FileTime, SetTimes};
+/// use std::path::Path;
+///
+/// SetTimes::new()
+///     .mtime(FileTime::from_unix_time(1_500_000_000, 0))
+///     .atime_now()
+///     .apply(Path::new("foo.txt"), true)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SetTimes {
+    atime: Option<FileTime>,
+    mtime: Option<FileTime>,
+    crtime: Option<FileTime>,
+    atime_now: bool,
+    mtime_now: bool,
+}
+
+impl SetTimes {
+    /// Creates a selection which doesn't set any times.
+    pub fn new() -> SetTimes {
+        SetTimes::default()
+    }
+
+    /// Sets the last access time to `atime`.
+    pub fn atime(mut self, atime: FileTime) -> SetTimes {
+        self.atime = Some(atime);
+        self.atime_now = false;
+        self
+    }
+
+    /// Sets the last modification time to `mtime`.
+    pub fn mtime(mut self, mtime: FileTime) -> SetTimes {
+        self.mtime = Some(mtime);
+        self.mtime_now = false;
+        self
+    }
+
+    /// Sets the creation time to `crtime`.
+    ///
+    /// This is only supported on Windows, and `apply` returns an error of
+    /// kind `io::ErrorKind::Unsupported` elsewhere.
+    pub fn crtime(mut self, crtime: FileTime) -> SetTimes {
+        self.crtime = Some(crtime);
+        self
+    }
+
+    /// Sets the last access time to the current time.
+    ///
+    /// See `apply` for how the current time is read.
+    pub fn atime_now(mut self) -> SetTimes {
+        self.atime = None;
+        self.atime_now = true;
+        self
+    }
+
+    /// Sets the last modification time to the current time.
+    ///
+    /// See `apply` for how the current time is read.
+    pub fn mtime_now(mut self) -> SetTimes {
+        self.mtime = None;
+        self.mtime_now = true;
+        self
+    }
+
+    /// Sets the selected times of the file at `p`.
+    ///
+    /// If `follow` is `false` and `p` is a symlink then the times of the
+    /// symlink itself are set, as by `set_symlink_file_times`. The times are
+    /// validated, as by `FileTime::validate_for_platform`, before anything is
+    /// written.
+    ///
+    /// Times selected with `atime_now` and `mtime_now` are set to the same
+    /// current time. On Unix they're passed to `utimensat` as `UTIME_NOW`, or
+    /// to `utimes` as no times at all when both are selected, so the kernel
+    /// reads the clock and only write access to the file is needed rather
+    /// than ownership of it. Elsewhere the current time is read beforehand and
+    /// set like any other time.
+    pub fn apply(&self, p: &Path, follow: bool) -> io::Result<()> {
+        for time in self
+            .atime
+            .iter()
+            .chain(self.mtime.iter())
+            .chain(self.crtime.iter())
+        {
+            time.validate_for_platform()?;
+        }
+        if self.crtime.is_none()
+            && self.atime.is_none()
+            && self.mtime.is_none()
+            && !self.atime_now
+            && !self.mtime_now
+        {
+            return Ok(());
+        }
+
+        cfg_if::cfg_if! {
+            if #[cfg(windows)] {
+                let (atime, mtime) = self.resolve_now();
+                imp::set_times(p, follow, self.crtime, atime, mtime)
+            } else {
+                if self.crtime.is_some() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        "setting the creation time is not supported on this platform",
+                    ));
+                }
+                self.apply_times(p, follow)
+            }
+        }
+    }
+
+    // Replaces the times selected with `atime_now` and `mtime_now` with a
+    // single reading of the clock.
+    #[cfg(not(all(unix, not(target_os = "redox"))))]
+    fn resolve_now(&self) -> (Option<FileTime>, Option<FileTime>) {
+        let now = if self.atime_now || self.mtime_now {
+            Some(FileTime::now())
+        } else {
+            None
+        };
+        let atime = if self.atime_now { now } else { self.atime };
+        let mtime = if self.mtime_now { now } else { self.mtime };
+        (atime, mtime)
+    }
+
+    #[cfg(all(unix, not(target_os = "redox")))]
+    fn apply_times(&self, p: &Path, follow: bool) -> io::Result<()> {
+        let arg = |time: Option<FileTime>, now: bool| {
+            if now {
+                imp::TimeArg::Now
+            } else {
+                time.map_or(imp::TimeArg::Omit, imp::TimeArg::At)
+            }
+        };
+        imp::set_time_args(
+            p,
+            arg(self.atime, self.atime_now),
+            arg(self.mtime, self.mtime_now),
+            !follow,
+        )
+    }
+
+    #[cfg(not(any(windows, all(unix, not(target_os = "redox")))))]
+    fn apply_times(&self, p: &Path, follow: bool) -> io::Result<()> {
+        let (atime, mtime) = self.resolve_now();
+        if follow {
+            return set_optional_file_times(p, atime, mtime);
+        }
+        let (atime, mtime) = match (atime, mtime) {
+            (Some(atime), Some(mtime)) => (atime, mtime),
+            (atime, mtime) => {
+                let meta = fs::symlink_metadata(p)?;
+                (
+                    atime.unwrap_or_else(|| FileTime::from_last_access_time(&meta)),
+                    mtime.unwrap_or_else(|| FileTime::from_last_modification_time(&meta)),
+                )
+            }
+        };
+        imp::set_symlink_file_times(p, atime, mtime)
+    }
+}
+
+/// Converts the selected times to a `std::fs::FileTimes`, for use with
+/// `File::set_times`.
+///
+/// `FileTimes` requires Rust 1.75, so this is only available with the
+/// `std-file-times` feature enabled. As with `apply`, times which aren't
+/// selected are left unchanged, and the creation time can only be selected on
+/// Windows. An error of kind `io::ErrorKind::InvalidInput` is returned if a
+/// time can't be represented by a `SystemTime`, such as some times before the
+/// Unix epoch on some platforms.
+///
+/// ```no_run
+/// # #[cfg(feature = "std-file-times")]
+/// # fn main() -> std::io::Result<()> {
+/// use filetime::{FileTime, SetTimes};
+/// use std::convert::TryInto;
+/// use std::fs::File;
+///
+/// let times = SetTimes::new()
+///     .atime(FileTime::from_unix_time(1_500_000_000, 0))
+///     .mtime(FileTime::from_unix_time(1_600_000_000, 0));
+/// File::options().write(true).open("foo.txt")?.set_times(times.try_into()?)?;
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "std-file-times"))]
+/// # fn main() {}
+/// ```
+#[cfg(feature = "std-file-times")]
+impl TryFrom<SetTimes> for fs::FileTimes {
+    type Error = io::Error;
+
+    fn try_from(times: SetTimes) -> io::Result<fs::FileTimes> {
+        let now = SystemTime::now();
+        let mut std_times = fs::FileTimes::new();
+        if times.atime_now {
+            std_times = std_times.set_accessed(now);
+        } else if let Some(atime) = times.atime {
+            std_times = std_times.set_accessed(SystemTime::try_from(atime)?);
+        }
+        if times.mtime_now {
+            std_times = std_times.set_modified(now);
+        } else if let Some(mtime) = times.mtime {
+            std_times = std_times.set_modified(SystemTime::try_from(mtime)?);
+        }
+        if let Some(crtime) = times.crtime {
+            cfg_if::cfg_if! {
+                if #[cfg(windows)] {
+                    use std::os::windows::fs::FileTimesExt;
+                    std_times = std_times.set_created(SystemTime::try_from(crtime)?);
+                } else {
+                    let _ = crtime;
+                    return Err(io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        "setting the creation time is not supported on this platform",
+                    ));
+                }
+            }
+        }
+        Ok(std_times)
+    }
+}
+
+/// Set the last modification time for a file on the filesystem.
+///
+/// This function will set the `mtime` metadata field for a file on the local
+/// filesystem, returning any error encountered.
+///
+/// # Platform support
+///
+/// Where supported this will attempt to issue just one syscall to update only
+/// the `mtime`, but where not supported this may issue one syscall to learn the
+/// existing `atime` so only the `mtime` can be configured.
+pub fn set_file_mtime<P>(p: P, mtime: FileTime) -> io::Result<()>
+where
+    P: AsRef<Path>,
+{
+    imp::set_file_mtime(p.as_ref(), mtime)
+}
+
+/// Set the last access time for a file on the filesystem.
+///
+/// This function will set the `atime` metadata field for a file on the local
+/// filesystem, returning any error encountered.
+///
+/// # Platform support
+///
+/// Where supported this will attempt to issue just one syscall to update only
+/// the `atime`, but where not supported this may issue one syscall to learn the
+/// existing `mtime` so only the `atime` can be configured.
+pub fn set_file_atime<P>(p: P, atime: FileTime) -> io::Result<()>
+where
+    P: AsRef<Path>,
+{
+    imp::set_file_atime(p.as_ref(), atime)
+}
+
+/// Copies the last modification time of `src` to `dst`, leaving the access
+/// time of `dst` unchanged.
+///
+/// Symlinks are followed for both paths, and the time is set with
+/// `set_file_mtime`.
+pub fn copy_file_mtime<P, Q>(src: P, dst: Q) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    set_file_mtime(dst, mtime_of(src)?)
+}
+
+/// Copies the last access time of `src` to `dst`, leaving the modification
+/// time of `dst` unchanged.
+///
+/// Symlinks are followed for both paths, and the time is set with
+/// `set_file_atime`.
+pub fn copy_file_atime<P, Q>(src: P, dst: Q) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    set_file_atime(dst, atime_of(src)?)
+}
+
+/// Set the last access time for a file on the filesystem, verifying that the
+/// filesystem actually stored it.
+///
+/// Some filesystems don't store access times, or only with very coarse
+/// precision, and silently ignore attempts to set them. This reads the
+/// access time back after setting it and returns an error if it doesn't
+/// match `atime` within `granularity`, the precision of the filesystem, as
+/// described for `times_match`.
+///
+/// There's no such verification in `set_file_times`, which writes both times
+/// with a single call, so the modification time is still updated if the
+/// filesystem ignores the access time. Note that when verification fails the
+/// access time has already been written, so it isn't rolled back.
+pub fn set_file_atime_verified<P>(p: P, atime: FileTime, granularity: Duration) -> io::Result<()>
+where
+    P: AsRef<Path>,
+{
+    let p = p.as_ref();
+    imp::set_file_atime(p, atime)?;
+    let stored = FileTime::from_last_access_time(&fs::metadata(p)?);
+    verify_atime(atime, stored, granularity)
+}
+
+fn verify_atime(requested: FileTime, stored: FileTime, granularity: Duration) -> io::Result<()> {
+    if within_granularity(&stored, &requested, granularity) {
+        return Ok(());
+    }
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "filesystem stored access time {} instead of {}, it may not support access times",
+            stored, requested
+        ),
+    ))
+}
+
+/// Set the last access time for a file on the filesystem, but only if the
+/// Linux `relatime` mount option would have updated it.
+///
+/// Explicitly setting the access time otherwise bypasses `relatime`. This
+/// instead only writes `atime` if the current access time is not later than
+/// the modification or status change time, or is at least 24 hours before
+/// `atime`, mimicking what the kernel does when the file is read. Returns
+/// whether the access time was updated.
+pub fn set_file_atime_relatime<P>(p: P, atime: FileTime) -> io::Result<bool>
+where
+    P: AsRef<Path>,
+{
+    let p = p.as_ref();
+    let meta = fs::metadata(p)?;
+    let update = relatime_would_update(
+        FileTime::from_last_access_time(&meta),
+        FileTime::from_last_modification_time(&meta),
+        imp::from_status_change_time(&meta),
+        atime,
+    );
+    if update {
+        imp::set_file_atime(p, atime)?;
+    }
+    Ok(update)
+}
+
+fn relatime_would_update(
+    current: FileTime,
+    mtime: FileTime,
+    ctime: Option<FileTime>,
+    now: FileTime,
+) -> bool {
+    if !current.is_after(&mtime) || ctime.is_some_and(|ctime| !current.is_after(&ctime)) {
+        return true;
+    }
+    match now.duration_since(&current) {
+        Some(elapsed) => elapsed >= Duration::from_secs(24 * 60 * 60),
+        None => false,
+    }
+}
+
+/// Set the last access and modification times for a file on the filesystem,
+/// skipping files whose filesystem doesn't allow it.
+///
+/// This is useful when walking a tree which may include read-only or virtual
+/// filesystems such as `/proc`, where a single failure shouldn't abort the
+/// whole operation. Returns `Ok(false)` if the file was skipped, which is when
+/// setting the times fails with `EROFS`, `EPERM` or `EOPNOTSUPP` on Unix, or
+/// with `ERROR_WRITE_PROTECT` or `ERROR_NOT_SUPPORTED` on Windows. Note that
+/// `EPERM` is also returned for files not owned by the caller, which are
+/// therefore skipped too.
+///
+/// Any other error includes the path of the file in its message, and keeps
+/// the kind of the original error.
+pub fn try_set_file_times_skip_unsupported<P>(
+    p: P,
+    atime: FileTime,
+    mtime: FileTime,
+) -> io::Result<bool>
+where
+    P: AsRef<Path>,
+{
+    let p = p.as_ref();
+    match imp::set_file_times(p, atime, mtime) {
+        Ok(()) => Ok(true),
+        Err(e) if imp::is_unsupported_error(&e) => Ok(false),
+        Err(e) => Err(io::Error::new(
+            e.kind(),
+            format!("failed to set times of {}: {}", p.display(), e),
+        )),
+    }
+}
+
+/// Set the last access and modification times for a file on the filesystem,
+/// but only where the new values are newer than what's currently on disk.
+///
+/// Each of `atime` and `mtime` which is `Some` is compared against the
+/// corresponding time currently stored for the file, and only those which
+/// are strictly newer are written. Returns whether any time was updated, which
+/// is `false` if neither time is newer, avoiding a needless write.
+///
+/// The times are validated before anything is written, so if this fails the
+/// file's times are unchanged.
+///
+/// Where times are set with `utimensat` or `futimens` on Unix, or with
+/// `SetFileTime` on Windows, they're written with a single call. The `utimes`
+/// family used elsewhere on Unix can only write both times, so if only one
+/// is written the other is first read with `stat`, and a change to it by
+/// something else in between is overwritten.
+pub fn set_file_times_if_newer<P>(
+    p: P,
+    atime: Option<FileTime>,
+    mtime: Option<FileTime>,
+) -> io::Result<bool>
+where
+    P: AsRef<Path>,
+{
+    let p = p.as_ref();
+    let meta = fs::metadata(p)?;
+    let newer = |new: Option<FileTime>, current: FileTime| {
+        new.filter(|new| new.cmp_normalized(&current) == Ordering::Greater)
+    };
+    let atime = newer(atime, FileTime::from_last_access_time(&meta));
+    let mtime = newer(mtime, FileTime::from_last_modification_time(&meta));
+    if atime.is_none() && mtime.is_none() {
+        return Ok(false);
+    }
+    set_optional_file_times(p, atime, mtime)?;
+    Ok(true)
+}
+
+/// The times stored by `set_file_times_checked` which differ from those
+/// requested.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrecisionWarning {
+    requested: (FileTime, FileTime),
+    stored: (FileTime, FileTime),
+}
+
+impl PrecisionWarning {
+    /// Returns the access and modification times that were requested.
+    pub fn requested(&self) -> (FileTime, FileTime) {
+        self.requested
+    }
+
+    /// Returns the access and modification times that the filesystem
+    /// actually stored.
+    pub fn stored(&self) -> (FileTime, FileTime) {
+        self.stored
+    }
+}
+
+impl fmt::Display for PrecisionWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "requested atime {} and mtime {} but the filesystem stored {} and {}",
+            self.requested.0, self.requested.1, self.stored.0, self.stored.1
+        )
+    }
+}
+
+/// Set the last access and modification times for a file on the filesystem,
+/// reporting whether the filesystem stored them with less precision.
+///
+/// This is the same as `set_file_times`, but afterwards reads the times back
+/// and returns a `PrecisionWarning` if they don't match what was requested.
+/// For example FAT only stores modification times with a granularity of two
+/// seconds, so setting a time with any nanoseconds there produces a warning,
+/// while filesystems with nanosecond precision such as ext4 or tmpfs (or
+/// 100ns on NTFS) won't.
+pub fn set_file_times_checked<P>(
+    p: P,
+    atime: FileTime,
+    mtime: FileTime,
+) -> io::Result<Option<PrecisionWarning>>
+where
+    P: AsRef<Path>,
+{
+    let p = p.as_ref();
+    imp::set_file_times(p, atime, mtime)?;
+    let meta = fs::metadata(p)?;
+    let stored = (
+        FileTime::from_last_access_time(&meta),
+        FileTime::from_last_modification_time(&meta),
+    );
+    if stored.0.cmp_normalized(&atime) == Ordering::Equal
+        && stored.1.cmp_normalized(&mtime) == Ordering::Equal
+    {
+        return Ok(None);
+    }
+    Ok(Some(PrecisionWarning {
+        requested: (atime, mtime),
+        stored,
+    }))
+}
+
+/// The status change time left by `set_file_times_ordered` which is newer
+/// than the modification time it set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CtimeInversion {
+    mtime: FileTime,
+    ctime: FileTime,
+}
+
+impl CtimeInversion {
+    /// Returns the modification time that was set.
+    pub fn mtime(&self) -> FileTime {
+        self.mtime
+    }
+
+    /// Returns the status change time the file was left with.
+    pub fn ctime(&self) -> FileTime {
+        self.ctime
+    }
+}
+
+impl fmt::Display for CtimeInversion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "the status change time {} is newer than the modification time {}",
+            self.ctime, self.mtime
+        )
+    }
+}
+
+/// Set the last access and modification times for a file on the filesystem,
+/// reporting whether the file's status change time is now newer than its
+/// modification time.
+///
+/// On Unix setting the times of a file updates its status change time
+/// (`ctime`) to the current time, and there's no way to set it directly. So
+/// restoring a modification time from the past always leaves a file which
+/// appears to have changed after it was last modified, which can confuse
+/// tools that compare the two. This is the same as `set_file_times`, but
+/// afterwards reads the status change time back and returns a
+/// `CtimeInversion` if it's newer than `mtime`, so callers such as forensic
+/// restores can tell when that ordering couldn't be preserved.
+///
+/// Platforms without a status change time, such as Windows, never produce a
+/// warning.
+pub fn set_file_times_ordered<P>(
+    p: P,
+    atime: FileTime,
+    mtime: FileTime,
+) -> io::Result<Option<CtimeInversion>>
+where
+    P: AsRef<Path>,
+{
+    let p = p.as_ref();
+    imp::set_file_times(p, atime, mtime)?;
+    let meta = fs::metadata(p)?;
+    Ok(imp::from_status_change_time(&meta)
+        .filter(|ctime| ctime.cmp_normalized(&mtime) == Ordering::Greater)
+        .map(|ctime| CtimeInversion { mtime, ctime }))
+}
+
+/// The times written by `set_file_times_clamped`, and whether either had to
+/// be clamped to fit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClampReport {
+    atime: FileTime,
+    mtime: FileTime,
+    atime_clamped: bool,
+    mtime_clamped: bool,
+}
+
+impl ClampReport {
+    /// Returns the access time which was written.
+    pub fn atime(&self) -> FileTime {
+        self.atime
+    }
+
+    /// Returns the modification time which was written.
+    pub fn mtime(&self) -> FileTime {
+        self.mtime
+    }
+
+    /// Returns whether the requested access time had to be clamped.
+    pub fn atime_clamped(&self) -> bool {
+        self.atime_clamped
+    }
+
+    /// Returns whether the requested modification time had to be clamped.
+    pub fn mtime_clamped(&self) -> bool {
+        self.mtime_clamped
+    }
+
+    /// Returns whether either of the requested times had to be clamped.
+    pub fn is_clamped(&self) -> bool {
+        self.atime_clamped || self.mtime_clamped
+    }
+}
+
+/// Set the last access and modification times for a file on the filesystem,
+/// clamping times which the platform can't represent.
+///
+/// This is intended for tools which prefer to do their best with absurd dates,
+/// such as those from untrusted archives, rather than fail. Times before the
+/// earliest time this platform's interface for setting times accepts are
+/// replaced with that time, and likewise for times after the latest. For
+/// example on Windows times can't be before 1601, and where `time_t` is only
+/// 32 bits times must be between 1901 and 2038. The returned `ClampReport`
+/// says what was written and whether either time was clamped.
+///
+/// Note that the filesystem may have a narrower range still, which is
+/// usually clamped silently by the OS.
+pub fn set_file_times_clamped<P>(
+    p: P,
+    atime: SystemTime,
+    mtime: SystemTime,
+) -> io::Result<ClampReport>
+where
+    P: AsRef<Path>,
+{
+    let (min, max) = platform_range();
+    let (atime, atime_clamped) = clamp_to_range(FileTime::from_system_time(atime), min, max);
+    let (mtime, mtime_clamped) = clamp_to_range(FileTime::from_system_time(mtime), min, max);
+    imp::set_file_times(p.as_ref(), atime, mtime)?;
+    Ok(ClampReport {
+        atime,
+        mtime,
+        atime_clamped,
+        mtime_clamped,
+    })
+}
+
+// The earliest and latest times that can be passed to the platform's
+// interface for setting times.
+#[allow(clippy::unnecessary_cast)] // `time_t` is only 32 bits on some platforms
+fn platform_range() -> (FileTime, FileTime) {
+    cfg_if::cfg_if! {
+        if #[cfg(windows)] {
+            // A `FILETIME` is a signed count of 100ns intervals since 1601.
+            let max = FileTime {
+                seconds: i64::MAX / 10_000_000,
+                nanos: (i64::MAX % 10_000_000) as u32 * 100,
+            };
+            (FileTime::zero(), max)
+        } else if #[cfg(unix)] {
+            let min = FileTime {
+                seconds: libc::time_t::MIN as i64,
+                nanos: 0,
+            };
+            let max = FileTime {
+                seconds: libc::time_t::MAX as i64,
+                nanos: 999_999_999,
+            };
+            (min, max)
+        } else {
+            let min = FileTime {
+                seconds: i64::MIN,
+                nanos: 0,
+            };
+            let max = FileTime {
+                seconds: i64::MAX,
+                nanos: 999_999_999,
+            };
+            (min, max)
+        }
+    }
+}
+
+fn clamp_to_range(time: FileTime, min: FileTime, max: FileTime) -> (FileTime, bool) {
+    if time < min {
+        (min, true)
+    } else if time > max {
+        (max, true)
+    } else {
+        (time, false)
+    }
+}
+
+/// Returns whether the modification time of a file matches `expected_mtime`,
+/// allowing for the precision of the filesystem.
+///
+/// `granularity` is the precision with which the filesystem stores times, for
+/// example two seconds for FAT or one nanosecond for ext4, and the times are
+/// considered to match if they differ by less than that. It can't reliably be
+/// inferred from the stored time, as a time on an exact second is just as
+/// likely to come from a filesystem with nanosecond precision. A zero
+/// granularity is treated as one nanosecond. This is useful to check whether
+/// a previous call to `set_file_mtime` took effect.
+pub fn times_match<P>(p: P, expected_mtime: FileTime, granularity: Duration) -> io::Result<bool>
+where
+    P: AsRef<Path>,
+{
+    let meta = fs::metadata(p)?;
+    let stored = FileTime::from_last_modification_time(&meta);
+    Ok(within_granularity(&stored, &expected_mtime, granularity))
+}
+
+// Whether two times differ by less than `granularity`, or are equal if it's
+// zero.
+fn within_granularity(a: &FileTime, b: &FileTime, granularity: Duration) -> bool {
+    let granularity = granularity.as_nanos().max(1) as i128;
+    a.nanos_diff(b).abs() < granularity
+}
+
+/// Truncates two times, each from a filesystem with the given granularity, to
+/// the coarser of the two granularities so that they can be compared.
+///
+/// For example when copying from ext4 with nanosecond granularity to FAT with
+/// two second granularity, the destination's modification time can never
+/// equal the source's unless the source's is also truncated to two seconds.
+/// Times are truncated towards the past, as most filesystems do when storing
+/// a time more precise than they support. A zero granularity is treated as
+/// one nanosecond.
+///
+/// # Panics
+///
+/// Panics if a truncated time can't be represented by a `FileTime`.
+pub fn normalize_for_comparison(
+    a: FileTime,
+    a_granularity: Duration,
+    b: FileTime,
+    b_granularity: Duration,
+) -> (FileTime, FileTime) {
+    let granularity = a_granularity.max(b_granularity).as_nanos().max(1) as i128;
+    let truncate = |time: FileTime| {
+        let nanos = time.unix_nanos();
+        FileTime::from_unix_nanos(nanos - nanos.rem_euclid(granularity))
+    };
+    (truncate(a), truncate(b))
+}
+
+/// Set the modification time for a file on the filesystem to the earliest
+/// time that's stored as strictly later than `floor`, returning the time
+/// that was stored.
+///
+/// This is useful to make a file appear newer than another one whose
+/// modification time is `floor`, even if both were written within the
+/// granularity of the filesystem. The precision of the filesystem isn't
+/// known up front, so increasingly large steps past `floor` are tried, from
+/// the platform's finest precision up to two seconds as used by FAT, reading
+/// back the stored time after each until it's later than `floor`.
+///
+/// Unlike most functions in this crate this may write the modification time
+/// several times, so if it fails part way through the last time written is
+/// left in place.
+pub fn bump_mtime_above<P>(p: P, floor: FileTime) -> io::Result<FileTime>
+where
+    P: AsRef<Path>,
+{
+    let p = p.as_ref();
+    let finest = if cfg!(windows) { 100 } else { 1 };
+    let steps = [
+        Duration::from_nanos(finest),
+        Duration::from_micros(1),
+        Duration::from_millis(1),
+        Duration::from_secs(1),
+        Duration::from_secs(2),
+    ];
+    for step in steps.iter() {
+        let candidate = FileTime::from_unix_nanos(floor.unix_nanos() + step.as_nanos() as i128);
+        imp::set_file_mtime(p, candidate)?;
+        let stored = FileTime::from_last_modification_time(&fs::metadata(p)?);
+        if stored.is_after(&floor) {
+            return Ok(stored);
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "filesystem didn't store a modification time later than {}",
+            floor
+        ),
+    ))
+}
+
+/// A change to the times of a file, computed by `plan_set_file_times` without
+/// modifying the file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetPlan {
+    path: PathBuf,
+    before: (FileTime, FileTime),
+    after: (FileTime, FileTime),
+}
+
+impl SetPlan {
+    /// Returns the canonicalized path of the file the plan applies to.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the access and modification times of the file at the time the
+    /// plan was made.
+    pub fn before(&self) -> (FileTime, FileTime) {
+        self.before
+    }
+
+    /// Returns the access and modification times the file will have once the
+    /// plan is executed.
+    pub fn after(&self) -> (FileTime, FileTime) {
+        self.after
+    }
+
+    /// Returns whether executing the plan would leave the file's times
+    /// unchanged.
+    pub fn is_noop(&self) -> bool {
+        self.before.0.cmp_normalized(&self.after.0) == Ordering::Equal
+            && self.before.1.cmp_normalized(&self.after.1) == Ordering::Equal
+    }
+
+    /// Sets the file's times to those in `after`.
+    ///
+    /// The file isn't checked again before writing, so if it was modified
+    /// since the plan was made those changes are overwritten.
+    pub fn execute(&self) -> io::Result<()> {
+        set_file_times(&self.path, self.after.0, self.after.1)
+    }
+}
+
+/// Computes the change `set_file_times` would make to a file, without
+/// modifying it.
+///
+/// This resolves the path and reads the file's current times, which is
+/// useful to preview or log changes, for example for a `--dry-run` mode. The
+/// returned plan can later be applied with `SetPlan::execute`.
+pub fn plan_set_file_times<P>(p: P, atime: FileTime, mtime: FileTime) -> io::Result<SetPlan>
+where
+    P: AsRef<Path>,
+{
+    let path = fs::canonicalize(p)?;
+    let meta = fs::metadata(&path)?;
+    Ok(SetPlan {
+        before: (
+            FileTime::from_last_access_time(&meta),
+            FileTime::from_last_modification_time(&meta),
+        ),
+        after: (atime, mtime),
+        path,
+    })
+}
+
+/// Set the last access and modification times for a file on the filesystem,
+/// returning the times it had before.
+///
+/// Either of `atime` and `mtime` may be `None` to leave that time unchanged.
+/// The returned access and modification times can later be passed to
+/// `set_file_times` to undo the change. On Windows the times are read and
+/// written through the same handle, while elsewhere they're read by path
+/// immediately before being written.
+///
+/// As with `update_file_times`, if this fails the file's times are unchanged.
+pub fn swap_file_times<P>(
+    p: P,
+    atime: Option<FileTime>,
+    mtime: Option<FileTime>,
+) -> io::Result<(FileTime, FileTime)>
+where
+    P: AsRef<Path>,
+{
+    let mut old = (FileTime::zero(), FileTime::zero());
+    update_file_times(p, |meta| {
+        old = (
+            FileTime::from_last_access_time(meta),
+            FileTime::from_last_modification_time(meta),
+        );
+        (atime, mtime)
+    })?;
+    Ok(old)
+}
+
+/// Set the last access and modification times for a file on the filesystem
+/// to values computed from its current metadata.
+///
+/// The file's metadata is read once and passed to `f`, which returns the new
+/// access and modification times, where `None` leaves that time unchanged.
+/// This is a building block for conditional updates, for example to only
+/// ever move the modification time forwards. On Windows the metadata is read
+/// and the times written through the same handle, while elsewhere the
+/// metadata is read by path immediately before writing the times.
+///
+/// The times returned by `f` are validated, as by
+/// `FileTime::validate_for_platform`, before anything is written. So if this
+/// returns an error the file's times are unchanged, although they may of
+/// course have been changed by something else between being read and
+/// written.
+///
+/// Where times are set with `utimensat` or `futimens` on Unix, or with
+/// `SetFileTime` on Windows, they're written with a single call. The `utimes`
+/// family used elsewhere on Unix can only write both times, so if only one
+/// is written the other is first read with `stat`, and a change to it by
+/// something else in between is overwritten.
+pub fn update_file_times<P, F>(p: P, f: F) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    F: FnOnce(&fs::Metadata) -> (Option<FileTime>, Option<FileTime>),
+{
+    let p = p.as_ref();
+    cfg_if::cfg_if! {
+        if #[cfg(windows)] {
+            imp::update_file_times(p, f)
+        } else {
+            let (atime, mtime) = f(&fs::metadata(p)?);
+            set_optional_file_times(p, atime, mtime)
+        }
+    }
+}
+
+fn set_optional_file_times(
+    p: &Path,
+    atime: Option<FileTime>,
+    mtime: Option<FileTime>,
+) -> io::Result<()> {
+    for time in atime.iter().chain(mtime.iter()) {
+        time.validate_for_platform()?;
+    }
+    match (atime, mtime) {
+        (Some(atime), Some(mtime)) => imp::set_file_times(p, atime, mtime),
+        (Some(atime), None) => imp::set_file_atime(p, atime),
+        (None, Some(mtime)) => imp::set_file_mtime(p, mtime),
+        (None, None) => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        atime_of, btime_of, copy_file_atime, copy_file_mtime, earliest_known_time,
+        from_metadata_tagged, get_file_times, get_symlink_file_times, handle_times, mtime_of,
+        newest_mtime, normalize_for_comparison, packed_mtime, plan_set_file_times, set_file_atime,
+        set_file_atime_relatime, set_file_handle_times, set_file_mtime, set_file_times,
+        set_file_times_checked, set_file_times_clamped, set_file_times_if_newer,
+        set_file_times_ordered, set_file_times_preserve_crtime, set_file_times_with_retry,
+        set_symlink_file_times, set_times_with_flags, supports_creation_time,
+        supports_setting_creation_time, swap_file_times, times_match, touch_existing,
+        touch_or_create, FileTime, RawTimes, SetTimes, StampOnClose, SymlinkFlag, TaggedTime,
+        TimeSource, UnixFileTime,
+    };
+    use std::fs::{self, File};
+    use std::io;
+    use std::path::Path;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    use tempfile::Builder;
+
+    #[cfg(unix)]
+    fn make_symlink_file<P, Q>(src: P, dst: Q) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        use std::os::unix::fs::symlink;
+        symlink(src, dst)
+    }
+
+    #[cfg(windows)]
+    fn make_symlink_file<P, Q>(src: P, dst: Q) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        use std::os::windows::fs::symlink_file;
+        symlink_file(src, dst)
+    }
+
+    #[cfg(unix)]
+    fn make_symlink_dir<P, Q>(src: P, dst: Q) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        use std::os::unix::fs::symlink;
+        symlink(src, dst)
+    }
+
+    #[cfg(windows)]
+    fn make_symlink_dir<P, Q>(src: P, dst: Q) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        use std::os::windows::fs::symlink_dir;
+        symlink_dir(src, dst)
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn from_unix_time_test() {
+        let time = FileTime::from_unix_time(10, 100_000_000);
+        assert_eq!(11644473610, time.seconds);
+        assert_eq!(100_000_000, time.nanos);
+
+        let time = FileTime::from_unix_time(-10, 100_000_000);
+        assert_eq!(11644473590, time.seconds);
+        assert_eq!(100_000_000, time.nanos);
+
+        let time = FileTime::from_unix_time(-12_000_000_000, 0);
+        assert_eq!(-355526400, time.seconds);
+        assert_eq!(0, time.nanos);
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn from_unix_time_test() {
+        let time = FileTime::from_unix_time(10, 100_000_000);
+        assert_eq!(10, time.seconds);
+        assert_eq!(100_000_000, time.nanos);
+
+        let time = FileTime::from_unix_time(-10, 100_000_000);
+        assert_eq!(-10, time.seconds);
+        assert_eq!(100_000_000, time.nanos);
+
+        let time = FileTime::from_unix_time(-12_000_000_000, 0);
+        assert_eq!(-12_000_000_000, time.seconds);
+        assert_eq!(0, time.nanos);
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn from_system_time_test() {
+        let time = FileTime::from_system_time(UNIX_EPOCH + Duration::from_secs(10));
+        assert_eq!(11644473610, time.seconds);
+        assert_eq!(0, time.nanos);
+
+        let time = FileTime::from_system_time(UNIX_EPOCH - Duration::from_secs(10));
+        assert_eq!(11644473590, time.seconds);
+        assert_eq!(0, time.nanos);
+
+        let time = FileTime::from_system_time(UNIX_EPOCH - Duration::from_millis(1100));
+        assert_eq!(11644473598, time.seconds);
+        assert_eq!(900_000_000, time.nanos);
+
+        let time = FileTime::from_system_time(UNIX_EPOCH - Duration::from_secs(12_000_000_000));
+        assert_eq!(-355526400, time.seconds);
+        assert_eq!(0, time.nanos);
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn from_system_time_test() {
+        let time = FileTime::from_system_time(UNIX_EPOCH + Duration::from_secs(10));
+        assert_eq!(10, time.seconds);
+        assert_eq!(0, time.nanos);
+
+        let time = FileTime::from_system_time(UNIX_EPOCH - Duration::from_secs(10));
+        assert_eq!(-10, time.seconds);
+        assert_eq!(0, time.nanos);
+
+        let time = FileTime::from_system_time(UNIX_EPOCH - Duration::from_millis(1100));
+        assert_eq!(-2, time.seconds);
+        assert_eq!(900_000_000, time.nanos);
+
+        let time = FileTime::from_system_time(UNIX_EPOCH - Duration::from_secs(12_000_000));
+        assert_eq!(-12_000_000, time.seconds);
+        assert_eq!(0, time.nanos);
+    }
+
+    #[test]
+    #[cfg_attr(
+        all(target_os = "linux", feature = "force-utimes"),
+        ignore = "requires nanosecond precision"
+    )]
+    fn set_file_times_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        let f = File::create(&path)?;
+
+        let metadata = fs::metadata(&path)?;
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        let atime = FileTime::from_last_access_time(&metadata);
+        set_file_times(&path, atime, mtime)?;
+
+        let new_mtime = FileTime::from_unix_time(10_000, 0);
+        set_file_times(&path, atime, new_mtime)?;
+
+        let metadata = fs::metadata(&path)?;
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        assert_eq!(mtime, new_mtime, "modification should be updated");
+
+        // Update just mtime
+        let new_mtime = FileTime::from_unix_time(20_000, 0);
+        set_file_handle_times(&f, None, Some(new_mtime))?;
+        let metadata = f.metadata()?;
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        assert_eq!(mtime, new_mtime, "modification time should be updated");
+        let new_atime = FileTime::from_last_access_time(&metadata);
+        assert_eq!(atime, new_atime, "accessed time should not be updated");
+
+        // Update just atime
+        let new_atime = FileTime::from_unix_time(30_000, 0);
+        set_file_handle_times(&f, Some(new_atime), None)?;
+        let metadata = f.metadata()?;
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        assert_eq!(mtime, new_mtime, "modification time should not be updated");
+        let atime = FileTime::from_last_access_time(&metadata);
+        assert_eq!(atime, new_atime, "accessed time should be updated");
+
+        let spath = td.path().join("bar.txt");
+        make_symlink_file(&path, &spath)?;
+        let metadata = fs::symlink_metadata(&spath)?;
+        let smtime = FileTime::from_last_modification_time(&metadata);
+
+        set_file_times(&spath, atime, mtime)?;
+
+        let metadata = fs::metadata(&path)?;
+        let cur_mtime = FileTime::from_last_modification_time(&metadata);
+        assert_eq!(mtime, cur_mtime);
+
+        let metadata = fs::symlink_metadata(&spath)?;
+        let cur_mtime = FileTime::from_last_modification_time(&metadata);
+        assert_eq!(smtime, cur_mtime);
+
+        set_file_times(&spath, atime, new_mtime)?;
+
+        let metadata = fs::metadata(&path)?;
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        assert_eq!(mtime, new_mtime);
+
+        let metadata = fs::symlink_metadata(&spath)?;
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        assert_eq!(mtime, smtime);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg_attr(
+        all(target_os = "linux", feature = "force-utimes"),
+        ignore = "requires nanosecond precision"
+    )]
+    fn set_dir_times_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo");
+        fs::create_dir(&path)?;
+
+        let metadata = fs::metadata(&path)?;
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        let atime = FileTime::from_last_access_time(&metadata);
+        set_file_times(&path, atime, mtime)?;
+
+        let new_mtime = FileTime::from_unix_time(10_000, 0);
+        set_file_times(&path, atime, new_mtime)?;
+
+        let metadata = fs::metadata(&path)?;
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        assert_eq!(mtime, new_mtime, "modification should be updated");
+
+        // Update just mtime
+        let new_mtime = FileTime::from_unix_time(20_000, 0);
+        set_file_mtime(&path, new_mtime)?;
+        let metadata = fs::metadata(&path)?;
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        assert_eq!(mtime, new_mtime, "modification time should be updated");
+        let new_atime = FileTime::from_last_access_time(&metadata);
+        assert_eq!(atime, new_atime, "accessed time should not be updated");
+
+        // Update just atime
+        let new_atime = FileTime::from_unix_time(30_000, 0);
+        set_file_atime(&path, new_atime)?;
+        let metadata = fs::metadata(&path)?;
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        assert_eq!(mtime, new_mtime, "modification time should not be updated");
+        let atime = FileTime::from_last_access_time(&metadata);
+        assert_eq!(atime, new_atime, "accessed time should be updated");
+
+        let spath = td.path().join("bar");
+        make_symlink_dir(&path, &spath)?;
+        let metadata = fs::symlink_metadata(&spath)?;
+        let smtime = FileTime::from_last_modification_time(&metadata);
+
+        set_file_times(&spath, atime, mtime)?;
+
+        let metadata = fs::metadata(&path)?;
+        let cur_mtime = FileTime::from_last_modification_time(&metadata);
+        assert_eq!(mtime, cur_mtime);
+
+        let metadata = fs::symlink_metadata(&spath)?;
+        let cur_mtime = FileTime::from_last_modification_time(&metadata);
+        assert_eq!(smtime, cur_mtime);
+
+        set_file_times(&spath, atime, new_mtime)?;
+
+        let metadata = fs::metadata(&path)?;
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        assert_eq!(mtime, new_mtime);
+
+        let metadata = fs::symlink_metadata(&spath)?;
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        assert_eq!(mtime, smtime);
+        Ok(())
+    }
+
+    #[test]
+    fn set_file_times_pre_unix_epoch_test() {
+        let td = Builder::new().prefix("filetime").tempdir().unwrap();
+        let path = td.path().join("foo.txt");
+        File::create(&path).unwrap();
+
+        let metadata = fs::metadata(&path).unwrap();
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        let atime = FileTime::from_last_access_time(&metadata);
+        set_file_times(&path, atime, mtime).unwrap();
+
+        let new_mtime = FileTime::from_unix_time(-10_000, 0);
+        if cfg!(target_os = "aix") {
+            // On AIX, os checks if the unix timestamp is valid.
+            let result = set_file_times(&path, atime, new_mtime);
+            assert!(result.is_err());
+            assert!(result.err().unwrap().kind() == std::io::ErrorKind::InvalidInput);
+        } else {
+            set_file_times(&path, atime, new_mtime).unwrap();
+
+            let metadata = fs::metadata(&path).unwrap();
+            let mtime = FileTime::from_last_modification_time(&metadata);
+            assert_eq!(mtime, new_mtime);
+        }
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn set_file_times_pre_windows_epoch_test() {
+        let td = Builder::new().prefix("filetime").tempdir().unwrap();
+        let path = td.path().join("foo.txt");
+        File::create(&path).unwrap();
+
+        let metadata = fs::metadata(&path).unwrap();
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        let atime = FileTime::from_last_access_time(&metadata);
+        set_file_times(&path, atime, mtime).unwrap();
+
+        let new_mtime = FileTime::from_unix_time(-12_000_000_000, 0);
+        assert!(set_file_times(&path, atime, new_mtime).is_err());
+    }
+
+    #[test]
+    fn set_symlink_file_times_test() {
+        let td = Builder::new().prefix("filetime").tempdir().unwrap();
+        let path = td.path().join("foo.txt");
+        File::create(&path).unwrap();
+
+        let metadata = fs::metadata(&path).unwrap();
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        let atime = FileTime::from_last_access_time(&metadata);
+        set_symlink_file_times(&path, atime, mtime).unwrap();
+
+        let new_mtime = FileTime::from_unix_time(10_000, 0);
+        set_symlink_file_times(&path, atime, new_mtime).unwrap();
+
+        let metadata = fs::metadata(&path).unwrap();
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        assert_eq!(mtime, new_mtime);
+
+        let spath = td.path().join("bar.txt");
+        make_symlink_file(&path, &spath).unwrap();
+
+        let metadata = fs::symlink_metadata(&spath).unwrap();
+        let smtime = FileTime::from_last_modification_time(&metadata);
+        let satime = FileTime::from_last_access_time(&metadata);
+        set_symlink_file_times(&spath, smtime, satime).unwrap();
+
+        let metadata = fs::metadata(&path).unwrap();
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        assert_eq!(mtime, new_mtime);
+
+        let new_smtime = FileTime::from_unix_time(20_000, 0);
+        set_symlink_file_times(&spath, atime, new_smtime).unwrap();
+
+        let metadata = fs::metadata(&spath).unwrap();
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        assert_eq!(mtime, new_mtime);
+
+        let metadata = fs::symlink_metadata(&spath).unwrap();
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        assert_eq!(mtime, new_smtime);
+    }
+
+    #[test]
+    fn set_symlink_dir_times_test() {
+        let td = Builder::new().prefix("filetime").tempdir().unwrap();
+        let path = td.path().join("foo");
+        fs::create_dir(&path).unwrap();
+
+        let metadata = fs::metadata(&path).unwrap();
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        let atime = FileTime::from_last_access_time(&metadata);
+        set_symlink_file_times(&path, atime, mtime).unwrap();
+
+        let new_mtime = FileTime::from_unix_time(10_000, 0);
+        set_symlink_file_times(&path, atime, new_mtime).unwrap();
+
+        let metadata = fs::metadata(&path).unwrap();
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        assert_eq!(mtime, new_mtime);
+
+        let spath = td.path().join("bar");
+        make_symlink_dir(&path, &spath).unwrap();
+
+        let metadata = fs::symlink_metadata(&spath).unwrap();
+        let smtime = FileTime::from_last_modification_time(&metadata);
+        let satime = FileTime::from_last_access_time(&metadata);
+        set_symlink_file_times(&spath, smtime, satime).unwrap();
+
+        let metadata = fs::metadata(&path).unwrap();
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        assert_eq!(mtime, new_mtime);
+
+        let new_smtime = FileTime::from_unix_time(20_000, 0);
+        set_symlink_file_times(&spath, atime, new_smtime).unwrap();
+
+        let metadata = fs::metadata(&spath).unwrap();
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        assert_eq!(mtime, new_mtime);
+
+        let metadata = fs::symlink_metadata(&spath).unwrap();
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        assert_eq!(mtime, new_smtime);
+    }
+
+    #[test]
+    #[cfg_attr(
+        all(target_os = "linux", feature = "force-utimes"),
+        ignore = "requires nanosecond precision"
+    )]
+    fn set_single_time_test() {
+        use super::{set_file_atime, set_file_mtime};
+
+        let td = Builder::new().prefix("filetime").tempdir().unwrap();
+        let path = td.path().join("foo.txt");
+        File::create(&path).unwrap();
+
+        let metadata = fs::metadata(&path).unwrap();
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        let atime = FileTime::from_last_access_time(&metadata);
+        set_file_times(&path, atime, mtime).unwrap();
+
+        let new_mtime = FileTime::from_unix_time(10_000, 0);
+        set_file_mtime(&path, new_mtime).unwrap();
+
+        let metadata = fs::metadata(&path).unwrap();
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        assert_eq!(mtime, new_mtime, "modification time should be updated");
+        assert_eq!(
+            atime,
+            FileTime::from_last_access_time(&metadata),
+            "access time should not be updated",
+        );
+
+        let new_atime = FileTime::from_unix_time(20_000, 0);
+        set_file_atime(&path, new_atime).unwrap();
+
+        let metadata = fs::metadata(&path).unwrap();
+        let atime = FileTime::from_last_access_time(&metadata);
+        assert_eq!(atime, new_atime, "access time should be updated");
+        assert_eq!(
+            mtime,
+            FileTime::from_last_modification_time(&metadata),
+            "modification time should not be updated"
+        );
+    }
+
+    #[test]
+    #[cfg(all(unix, not(target_os = "redox")))]
+    fn to_time_t_test() {
+        use super::imp::to_time_t;
+
+        assert!(to_time_t(10_000).is_ok());
+        assert!(to_time_t(-10_000).is_ok());
+
+        let post_2038 = i64::from(i32::MAX) + 1;
+        if std::mem::size_of::<libc::time_t>() == 4 {
+            let err = to_time_t(post_2038).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        } else {
+            assert!(to_time_t(post_2038).is_ok());
+        }
+    }
+
+    #[test]
+    fn duration_since_test() {
+        let earlier = FileTime::from_unix_time(10, 900_000_000);
+        let later = FileTime::from_unix_time(12, 100_000_000);
+        assert_eq!(
+            later.duration_since(&earlier),
+            Some(Duration::from_millis(1200))
+        );
+        assert_eq!(later.duration_since(&later), Some(Duration::from_secs(0)));
+        assert_eq!(earlier.duration_since(&later), None);
+
+        let pre_epoch = FileTime::from_unix_time(-1, 500_000_000);
+        assert_eq!(
+            earlier.duration_since(&pre_epoch),
+            Some(Duration::from_millis(11_400))
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn from_timespec_test() {
+        let mut ts: libc::timespec = unsafe { std::mem::zeroed() };
+        ts.tv_sec = 10_000;
+        ts.tv_nsec = 123_456_789;
+        let time = FileTime::from_timespec(ts);
+        assert_eq!(10_000, time.seconds());
+        assert_eq!(123_456_789, time.nanoseconds());
+        assert_eq!(time, FileTime::from_unix_time(10_000, 123_456_789));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn from_timeval_test() {
+        let mut tv: libc::timeval = unsafe { std::mem::zeroed() };
+        tv.tv_sec = -10_000;
+        tv.tv_usec = 123_456;
+        let time = FileTime::from_timeval(tv);
+        assert_eq!(-10_000, time.seconds());
+        assert_eq!(123_456_000, time.nanoseconds());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn from_timespec_out_of_range_test() {
+        let mut ts: libc::timespec = unsafe { std::mem::zeroed() };
+        ts.tv_sec = 10_000;
+        ts.tv_nsec = -2;
+        let time = FileTime::from_timespec(ts);
+        assert_eq!((time.seconds(), time.nanoseconds()), (9_999, 999_999_998));
+
+        // Small enough for the 32-bit `tv_nsec` of some targets.
+        ts.tv_nsec = 1_500_000_000;
+        let time = FileTime::from_timespec(ts);
+        assert_eq!((time.seconds(), time.nanoseconds()), (10_001, 500_000_000));
+
+        ts.tv_sec = libc::time_t::MAX;
+        ts.tv_nsec = 1_000_000_000;
+        let time = FileTime::from_timespec(ts);
+        assert!(time.nanoseconds() < 1_000_000_000);
+
+        let mut tv: libc::timeval = unsafe { std::mem::zeroed() };
+        tv.tv_sec = 10_000;
+        tv.tv_usec = -1;
+        let time = FileTime::from_timeval(tv);
+        assert_eq!((time.seconds(), time.nanoseconds()), (9_999, 999_999_000));
+        tv.tv_usec = 1_000_001;
+        let time = FileTime::from_timeval(tv);
+        assert_eq!((time.seconds(), time.nanoseconds()), (10_001, 1_000));
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn from_filetime_raw_test() {
+        let time = FileTime::from_unix_time(10_000, 123_456_700);
+        let intervals = time.seconds() as u64 * 10_000_000 + time.nanoseconds() as u64 / 100;
+        let raw = FileTime::from_filetime_raw(intervals as u32, (intervals >> 32) as u32);
+        assert_eq!(raw, time);
+    }
+
+    #[test]
+    fn set_file_times_preserve_crtime_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+
+        let metadata = fs::metadata(&path)?;
+        let crtime = FileTime::from_creation_time(&metadata);
+
+        let new_atime = FileTime::from_unix_time(10_000, 0);
+        let new_mtime = FileTime::from_unix_time(20_000, 0);
+        set_file_times_preserve_crtime(&path, new_atime, new_mtime)?;
+
+        let metadata = fs::metadata(&path)?;
+        assert_eq!(FileTime::from_last_access_time(&metadata), new_atime);
+        assert_eq!(FileTime::from_last_modification_time(&metadata), new_mtime);
+        if cfg!(windows) {
+            assert_eq!(FileTime::from_creation_time(&metadata), crtime);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn validate_for_platform_test() {
+        assert!(FileTime::zero().validate_for_platform().is_ok());
+        assert!(FileTime::now().validate_for_platform().is_ok());
+        assert!(FileTime::from_unix_time(-10_000, 0)
+            .validate_for_platform()
+            .is_ok());
+
+        let bad_nanos = FileTime {
+            seconds: 0,
+            nanos: 1_000_000_000,
+        };
+        let err = bad_nanos.validate_for_platform().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        let pre_1601 = FileTime::from_unix_time(-12_000_000_000, 0);
+        assert_eq!(pre_1601.validate_for_platform().is_err(), cfg!(windows));
+
+        let far_future = FileTime {
+            seconds: i64::MAX,
+            nanos: 0,
+        };
+        #[cfg(unix)]
+        let time_t_is_32_bit = std::mem::size_of::<libc::time_t>() == 4;
+        #[cfg(not(unix))]
+        let time_t_is_32_bit = false;
+        assert_eq!(
+            far_future.validate_for_platform().is_err(),
+            cfg!(windows) || time_t_is_32_bit
+        );
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn windows_nanos_quantized_test() -> io::Result<()> {
+        let time = FileTime::from_unix_time(10_000, 123_456_789);
+        assert_eq!(123_456_700, time.nanoseconds());
+
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+        set_file_times(&path, time, time)?;
+
+        let metadata = fs::metadata(&path)?;
+        let times = [
+            Some(FileTime::from_last_modification_time(&metadata)),
+            Some(FileTime::from_last_access_time(&metadata)),
+            FileTime::from_creation_time(&metadata),
+            Some(FileTime::now()),
+        ];
+        for time in times.iter().flatten() {
+            assert_eq!(time.nanoseconds() % 100, 0);
+        }
+        assert_eq!(FileTime::from_last_modification_time(&metadata), time);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(unix, not(target_os = "redox")))]
+    fn set_fifo_times_test() -> io::Result<()> {
+        use std::ffi::CString;
+        use std::os::unix::prelude::*;
+
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("fifo");
+        let c_path = CString::new(path.as_os_str().as_bytes())?;
+        if unsafe { libc::mkfifo(c_path.as_ptr(), 0o644) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let new_atime = FileTime::from_unix_time(10_000, 0);
+        let new_mtime = FileTime::from_unix_time(20_000, 0);
+        set_file_times(&path, new_atime, new_mtime)?;
+
+        let metadata = fs::metadata(&path)?;
+        assert!(metadata.file_type().is_fifo());
+        assert_eq!(FileTime::from_last_access_time(&metadata), new_atime);
+        assert_eq!(FileTime::from_last_modification_time(&metadata), new_mtime);
+
+        let new_mtime = FileTime::from_unix_time(30_000, 0);
+        set_file_mtime(&path, new_mtime)?;
+        let metadata = fs::metadata(&path)?;
+        assert_eq!(FileTime::from_last_access_time(&metadata), new_atime);
+        assert_eq!(FileTime::from_last_modification_time(&metadata), new_mtime);
+        Ok(())
+    }
+
+    #[test]
+    fn cmp_normalized_test() {
+        use std::cmp::Ordering;
+
+        // On Windows these straddle the 1601 epoch, so the raw seconds
+        // change sign between the two values.
+        let before = FileTime::from_unix_time(-11_644_473_601, 0);
+        let after = FileTime::from_unix_time(-11_644_473_599, 0);
+        assert_eq!(before.cmp_normalized(&after), Ordering::Less);
+        assert_eq!(after.cmp_normalized(&before), Ordering::Greater);
+        assert_eq!(before.cmp_normalized(&before), Ordering::Equal);
+        assert_eq!(before.cmp(&after), before.cmp_normalized(&after));
+
+        let a = FileTime::from_unix_time(10, 500_000_000);
+        let b = FileTime::from_unix_time(10, 0);
+        assert_eq!(a.cmp_normalized(&b), Ordering::Greater);
+    }
+
+    #[test]
+    fn set_file_times_if_newer_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+
+        let atime = FileTime::from_unix_time(10_000, 0);
+        let mtime = FileTime::from_unix_time(20_000, 0);
+        set_file_times(&path, atime, mtime)?;
+
+        // Older and equal times are skipped
+        let older = FileTime::from_unix_time(5_000, 0);
+        assert!(!set_file_times_if_newer(&path, Some(older), Some(mtime))?);
+        assert!(!set_file_times_if_newer(&path, None, None)?);
+        let metadata = fs::metadata(&path)?;
+        assert_eq!(FileTime::from_last_access_time(&metadata), atime);
+        assert_eq!(FileTime::from_last_modification_time(&metadata), mtime);
+
+        // Only the newer of the two is written
+        let newer = FileTime::from_unix_time(30_000, 0);
+        assert!(set_file_times_if_newer(&path, Some(older), Some(newer))?);
+        let metadata = fs::metadata(&path)?;
+        assert_eq!(FileTime::from_last_access_time(&metadata), atime);
+        assert_eq!(FileTime::from_last_modification_time(&metadata), newer);
+
+        // A single field is compared on its own
+        assert!(set_file_times_if_newer(&path, Some(newer), None)?);
+        let metadata = fs::metadata(&path)?;
+        assert_eq!(FileTime::from_last_access_time(&metadata), newer);
+        assert_eq!(FileTime::from_last_modification_time(&metadata), newer);
+        Ok(())
+    }
+
+    #[test]
+    fn supports_creation_time_test() -> io::Result<()> {
+        let expected = cfg!(any(
+            windows,
+            all(target_os = "linux", target_env = "gnu"),
+            target_os = "macos",
+            target_os = "freebsd",
+        ));
+        assert_eq!(supports_creation_time(), expected);
+        assert_eq!(supports_setting_creation_time(), cfg!(windows));
+
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+        let crtime = FileTime::from_creation_time(&fs::metadata(&path)?);
+        if !supports_creation_time() {
+            assert_eq!(crtime, None);
+        } else if cfg!(any(windows, target_os = "macos")) {
+            assert!(crtime.is_some());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn set_file_times_with_retry_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+
+        let new_mtime = FileTime::from_unix_time(10_000, 0);
+        set_file_times_with_retry(&path, new_mtime, new_mtime, 3, Duration::from_millis(1))?;
+        let metadata = fs::metadata(&path)?;
+        assert_eq!(FileTime::from_last_modification_time(&metadata), new_mtime);
+
+        let missing = td.path().join("missing.txt");
+        let err = set_file_times_with_retry(&missing, new_mtime, new_mtime, 3, Duration::ZERO)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn retry_on_sharing_violation_test() {
+        use super::imp::retry_on_sharing_violation;
+
+        let sharing_violation = || io::Error::from_raw_os_error(32);
+
+        let mut failures = 2;
+        let mut calls = 0;
+        retry_on_sharing_violation(3, Duration::ZERO, || {
+            calls += 1;
+            if failures > 0 {
+                failures -= 1;
+                Err(sharing_violation())
+            } else {
+                Ok(())
+            }
+        })
+        .unwrap();
+        assert_eq!(calls, 3);
+
+        let mut calls = 0;
+        let err = retry_on_sharing_violation(2, Duration::ZERO, || {
+            calls += 1;
+            Err(sharing_violation())
+        })
+        .unwrap_err();
+        assert_eq!(calls, 3);
+        assert!(err.to_string().contains("in use by another process"));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn linux_enosys_fallback_test() {
+        use super::imp::{utimensat_unsupported, with_fallback};
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let unsupported = AtomicBool::new(false);
+        let mut fallbacks = 0;
+
+        // `ENOSYS` switches over to the fallback ...
+        with_fallback(
+            &unsupported,
+            || Err(io::Error::from_raw_os_error(libc::ENOSYS)),
+            || {
+                fallbacks += 1;
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert!(unsupported.load(Ordering::SeqCst));
+        assert_eq!(fallbacks, 1);
+
+        // ... and later calls go straight to it.
+        with_fallback(
+            &unsupported,
+            || panic!("should not be attempted"),
+            || Ok(()),
+        )
+        .unwrap();
+
+        // Other errors are returned as-is without poisoning the state.
+        let unsupported = AtomicBool::new(false);
+        let err = with_fallback(
+            &unsupported,
+            || Err(io::Error::from_raw_os_error(libc::EPERM)),
+            || panic!("should not fall back"),
+        )
+        .unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EPERM));
+        assert!(!unsupported.load(Ordering::SeqCst));
+
+        // None of the above affected the process-wide state.
+        assert_eq!(utimensat_unsupported(), (false, false));
+    }
+
+    #[test]
+    fn from_epoch_offset_test() {
+        // One GPS week after the GPS epoch, 1980-01-13T00:00:00Z
+        let gps_epoch = 315_964_800;
+        let time = FileTime::from_epoch_offset(gps_epoch, 7 * 24 * 60 * 60, 500_000);
+        assert_eq!(time.unix_seconds(), 316_569_600);
+        assert_eq!(time, FileTime::from_unix_time(316_569_600, 500_000));
+
+        let time = FileTime::from_epoch_offset(gps_epoch, -gps_epoch, 0);
+        assert_eq!(time.unix_seconds(), 0);
+    }
+
+    #[test]
+    fn get_file_times_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+        set_file_times(
+            &path,
+            FileTime::from_unix_time(10_000, 0),
+            FileTime::from_unix_time(20_000, 0),
+        )?;
+
+        let spath = td.path().join("bar.txt");
+        make_symlink_file(&path, &spath)?;
+
+        let metadata = fs::metadata(&path)?;
+        let expected = (
+            FileTime::from_last_access_time(&metadata),
+            FileTime::from_last_modification_time(&metadata),
+            FileTime::from_creation_time(&metadata),
+        );
+        assert_eq!(get_file_times(&path)?, expected);
+        assert_eq!(get_file_times(&spath)?, expected);
+
+        let metadata = fs::symlink_metadata(&spath)?;
+        let expected = (
+            FileTime::from_last_access_time(&metadata),
+            FileTime::from_last_modification_time(&metadata),
+            FileTime::from_creation_time(&metadata),
+        );
+        assert_eq!(get_symlink_file_times(&spath)?, expected);
+        assert_ne!(get_symlink_file_times(&spath)?.1, get_file_times(&spath)?.1);
+        Ok(())
+    }
+
+    #[test]
+    fn with_nanos_and_seconds_test() {
+        let time = FileTime::zero()
+            .with_seconds(10_000)
+            .with_nanos(500_000_000);
+        assert_eq!(time.unix_seconds(), 10_000);
+        assert_eq!(time.nanoseconds(), 500_000_000);
+        assert_eq!(time, FileTime::from_unix_time(10_000, 500_000_000));
+
+        let time = time.with_seconds(-10);
+        assert_eq!(time.unix_seconds(), -10);
+        assert_eq!(time.nanoseconds(), 500_000_000);
+    }
+
+    #[test]
+    #[should_panic]
+    fn with_nanos_out_of_range_test() {
+        FileTime::zero().with_nanos(1_000_000_000);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn set_file_times_cstr_test() -> io::Result<()> {
+        use super::set_file_times_cstr;
+        use std::ffi::CString;
+        use std::os::unix::prelude::*;
+
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+
+        let cpath = CString::new(path.as_os_str().as_bytes())?;
+        let atime = FileTime::from_unix_time(10_000, 0);
+        let mtime = FileTime::from_unix_time(20_000, 0);
+        set_file_times_cstr(&cpath, atime, mtime)?;
+
+        let metadata = fs::metadata(&path)?;
+        assert_eq!(FileTime::from_last_access_time(&metadata), atime);
+        assert_eq!(FileTime::from_last_modification_time(&metadata), mtime);
+        Ok(())
+    }
+
+    #[test]
+    fn unix_file_time_test() {
+        let ft = FileTime::from_unix_time(1_234, 500_000);
+        let unix = UnixFileTime::from(ft);
+        assert_eq!(unix, UnixFileTime::new(1_234, 500_000));
+        assert_eq!(unix.seconds(), 1_234);
+        assert_eq!(unix.nanoseconds(), 500_000);
+        assert_eq!(FileTime::from(unix), ft);
+
+        let before = UnixFileTime::from(FileTime::from_unix_time(-1, 999_999_900));
+        assert_eq!(before.seconds(), -1);
+        assert!(before < unix);
+        assert_eq!(
+            FileTime::from(before),
+            FileTime::from_unix_time(-1, 999_999_900)
+        );
+
+        assert_eq!(UnixFileTime::new(1_234, 500).to_string(), "1234.000000500s");
+        assert_eq!(UnixFileTime::new(-5, 0).to_string(), "-5.000000000s");
+    }
+
+    #[test]
+    #[cfg(any(
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "tvos",
+        target_os = "watchos",
+        target_os = "visionos"
+    ))]
+    fn apple_creation_time_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+
+        let metadata = fs::metadata(&path)?;
+        assert!(FileTime::from_creation_time(&metadata).is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn rounded_to_test() {
+        let second = Duration::from_secs(1);
+        let round = |s, ns| FileTime::from_unix_time(s, ns).rounded_to(second);
+        assert_eq!(round(10, 0), FileTime::from_unix_time(10, 0));
+        assert_eq!(round(10, 499_999_900), FileTime::from_unix_time(10, 0));
+        assert_eq!(round(10, 500_000_000), FileTime::from_unix_time(11, 0));
+        assert_eq!(round(-11, 500_000_000), FileTime::from_unix_time(-11, 0));
+        assert_eq!(round(-11, 600_000_000), FileTime::from_unix_time(-10, 0));
+
+        let two = Duration::from_secs(2);
+        let round = |s, ns| FileTime::from_unix_time(s, ns).rounded_to(two);
+        assert_eq!(round(10, 999_999_900), FileTime::from_unix_time(10, 0));
+        assert_eq!(round(11, 0), FileTime::from_unix_time(12, 0));
+        assert_eq!(round(11, 100), FileTime::from_unix_time(12, 0));
+        assert_eq!(round(-1, 0), FileTime::from_unix_time(-2, 0));
+        assert_eq!(round(-3, 0), FileTime::from_unix_time(-4, 0));
+
+        let uneven = Duration::from_millis(1_500);
+        assert_eq!(
+            FileTime::from_unix_time(2, 0).rounded_to(uneven),
+            FileTime::from_unix_time(1, 500_000_000)
+        );
+
+        let ft = FileTime::from_unix_time(10, 123_456_700);
+        assert_eq!(ft.rounded_to(Duration::from_secs(0)), ft);
+    }
+
+    #[test]
+    fn plan_set_file_times_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+        let atime = FileTime::from_unix_time(10_000, 0);
+        let mtime = FileTime::from_unix_time(20_000, 0);
+        set_file_times(&path, atime, mtime)?;
+
+        let new_atime = FileTime::from_unix_time(30_000, 0);
+        let new_mtime = FileTime::from_unix_time(40_000, 0);
+        let plan = plan_set_file_times(&path, new_atime, new_mtime)?;
+        assert_eq!(plan.path(), fs::canonicalize(&path)?);
+        assert_eq!(plan.before(), (atime, mtime));
+        assert_eq!(plan.after(), (new_atime, new_mtime));
+        assert!(!plan.is_noop());
+
+        let metadata = fs::metadata(&path)?;
+        assert_eq!(FileTime::from_last_access_time(&metadata), atime);
+        assert_eq!(FileTime::from_last_modification_time(&metadata), mtime);
+
+        plan.execute()?;
+        let metadata = fs::metadata(&path)?;
+        assert_eq!(FileTime::from_last_access_time(&metadata), new_atime);
+        assert_eq!(FileTime::from_last_modification_time(&metadata), new_mtime);
+
+        assert!(plan_set_file_times(&path, new_atime, new_mtime)?.is_noop());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn set_read_only_handle_times_test() -> io::Result<()> {
+        use super::reopen_with_write_attributes;
+
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+
+        let f = File::open(&path)?;
+        let mtime = FileTime::from_unix_time(10_000, 0);
+        let err = set_file_handle_times(&f, None, Some(mtime)).unwrap_err();
+        assert!(err.to_string().contains("FILE_WRITE_ATTRIBUTES"), "{}", err);
+
+        let f = reopen_with_write_attributes(&f)?;
+        set_file_handle_times(&f, None, Some(mtime))?;
+        let metadata = fs::metadata(&path)?;
+        assert_eq!(FileTime::from_last_modification_time(&metadata), mtime);
+        Ok(())
+    }
+
+    #[test]
+    fn set_hardlink_file_times_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+        let link = td.path().join("bar.txt");
+        fs::hard_link(&path, &link)?;
+
+        let atime = FileTime::from_unix_time(10_000, 0);
+        let mtime = FileTime::from_unix_time(20_000, 0);
+        set_file_times(&link, atime, mtime)?;
+
+        for p in [&path, &link].iter() {
+            let metadata = fs::metadata(p)?;
+            assert_eq!(FileTime::from_last_access_time(&metadata), atime);
+            assert_eq!(FileTime::from_last_modification_time(&metadata), mtime);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn packed_mtime_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+        let mtime = FileTime::from_unix_time(20_000, 123_456_700);
+        set_file_mtime(&path, mtime)?;
+
+        let metadata = fs::metadata(&path)?;
+        let packed = packed_mtime(&metadata);
+        let expected = FileTime::from_last_modification_time(&metadata);
+        assert_eq!(packed.mtime_secs, expected.unix_seconds());
+        assert_eq!(packed.mtime_nanos, expected.nanoseconds());
+        assert_eq!(packed.mtime_secs, 20_000);
+        Ok(())
+    }
+
+    #[test]
+    fn is_before_and_after_test() {
+        // Straddles the 1601 epoch on Windows, as in `cmp_normalized_test`.
+        let before = FileTime::from_unix_time(-11_644_473_601, 0);
+        let after = FileTime::from_unix_time(-11_644_473_599, 0);
+        assert!(before.is_before(&after));
+        assert!(!before.is_after(&after));
+        assert!(after.is_after(&before));
+        assert!(!after.is_before(&before));
+        assert!(!before.is_before(&before));
+        assert!(!before.is_after(&before));
+
+        let unix = FileTime::from_unix_time(0, 0);
+        assert!(unix.is_after(&after));
+        assert!(FileTime::from_unix_time(0, 100).is_after(&unix));
+    }
+
+    #[test]
+    #[cfg(target_os = "redox")]
+    fn redox_invalid_nanos_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+
+        let invalid = FileTime {
+            seconds: 0,
+            nanos: 3_000_000_000,
+        };
+        let err = set_file_times(&path, invalid, invalid).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn since_windows_epoch_test() {
+        let intervals: u64 = 0x01d9_5c7a_1234_5678;
+        let ft = FileTime::from_filetime_raw(intervals as u32, (intervals >> 32) as u32);
+        let since = ft.since_windows_epoch().unwrap();
+        assert_eq!(since, Duration::from_nanos(intervals * 100));
+        assert_eq!(FileTime::from_duration_since_windows_epoch(since), ft);
+
+        assert_eq!(
+            FileTime::from_unix_time(-11_644_473_600, 0).since_windows_epoch(),
+            Some(Duration::from_secs(0))
+        );
+        assert_eq!(
+            FileTime::from_unix_time(-11_644_473_601, 0).since_windows_epoch(),
+            None
+        );
+    }
+
+    #[test]
+    #[cfg_attr(
+        all(target_os = "linux", feature = "force-utimes"),
+        ignore = "requires nanosecond precision"
+    )]
+    fn set_file_times_checked_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+
+        // The temporary directory is expected to be on a filesystem with at
+        // least 100ns precision, unlike FAT which would produce a warning.
+        let atime = FileTime::from_unix_time(10_000, 123_456_700);
+        let mtime = FileTime::from_unix_time(20_000, 765_432_100);
+        assert_eq!(set_file_times_checked(&path, atime, mtime)?, None);
+
+        let metadata = fs::metadata(&path)?;
+        assert_eq!(FileTime::from_last_modification_time(&metadata), mtime);
+        Ok(())
+    }
+
+    #[test]
+    fn earliest_known_time_test() -> io::Result<()> {
+        use super::earliest_of;
+
+        let early = FileTime::from_unix_time(10_000, 0);
+        let middle = FileTime::from_unix_time(20_000, 0);
+        let late = FileTime::from_unix_time(30_000, 0);
+        assert_eq!(earliest_of(Some(middle), Some(early), late), middle);
+        assert_eq!(earliest_of(None, Some(early), late), early);
+        assert_eq!(earliest_of(None, Some(late), early), early);
+        assert_eq!(earliest_of(None, None, middle), middle);
+
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+        set_file_mtime(&path, early)?;
+        let metadata = fs::metadata(&path)?;
+        let expected = FileTime::from_creation_time(&metadata).unwrap_or(early);
+        assert_eq!(earliest_known_time(&metadata), expected);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn set_long_path_file_times_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let mut dir = td.path().to_path_buf();
+        for _ in 0..10 {
+            dir.push("a".repeat(40));
+        }
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("foo.txt");
+        assert!(path.as_os_str().len() > 260);
+        File::create(&path)?;
+
+        let atime = FileTime::from_unix_time(10_000, 0);
+        let mtime = FileTime::from_unix_time(20_000, 0);
+        set_file_times(&path, atime, mtime)?;
+        let metadata = fs::metadata(&path)?;
+        assert_eq!(FileTime::from_last_modification_time(&metadata), mtime);
+        Ok(())
+    }
+
+    #[test]
+    fn max_of_and_min_of_test() {
+        let times = [
+            FileTime::from_unix_time(20_000, 0),
+            FileTime::from_unix_time(-11_644_473_601, 0),
+            FileTime::from_unix_time(30_000, 500),
+            FileTime::from_unix_time(30_000, 0),
+        ];
+        assert_eq!(
+            FileTime::max_of(times.iter().copied()),
+            Some(FileTime::from_unix_time(30_000, 500))
+        );
+        assert_eq!(
+            FileTime::min_of(times.iter().copied()),
+            Some(FileTime::from_unix_time(-11_644_473_601, 0))
+        );
+        assert_eq!(FileTime::max_of(Vec::new()), None);
+        assert_eq!(FileTime::min_of(Vec::new()), None);
+    }
+
+    #[test]
+    fn set_file_atime_relatime_test() -> io::Result<()> {
+        use super::relatime_would_update;
+
+        let day = 24 * 60 * 60;
+        let t = |s| FileTime::from_unix_time(s, 0);
+        assert!(relatime_would_update(t(100), t(100), None, t(200)));
+        assert!(relatime_would_update(t(100), t(150), None, t(200)));
+        assert!(relatime_would_update(t(200), t(100), Some(t(300)), t(400)));
+        assert!(relatime_would_update(t(200), t(100), None, t(200 + day)));
+        assert!(!relatime_would_update(t(200), t(100), None, t(199 + day)));
+        assert!(!relatime_would_update(t(200), t(100), Some(t(100)), t(300)));
+
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+
+        // The access time is later than both the modification and status
+        // change times, and less than a day older than the new one, so isn't
+        // updated.
+        let now = FileTime::now().unix_seconds();
+        let atime = FileTime::from_unix_time(now + 3_600, 0);
+        let mtime = FileTime::from_unix_time(now - 60, 0);
+        set_file_times(&path, atime, mtime)?;
+        let later = FileTime::from_unix_time(now + 3_660, 0);
+        assert!(!set_file_atime_relatime(&path, later)?);
+        let metadata = fs::metadata(&path)?;
+        assert_eq!(FileTime::from_last_access_time(&metadata), atime);
+
+        // An access time that's not later than the modification time is.
+        set_file_times(&path, mtime, mtime)?;
+        assert!(set_file_atime_relatime(&path, later)?);
+        let metadata = fs::metadata(&path)?;
+        assert_eq!(FileTime::from_last_access_time(&metadata), later);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(any(
+        target_os = "aix",
+        target_os = "solaris",
+        target_os = "illumos",
+        target_os = "emscripten",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "haiku"
+    ))]
+    fn set_file_handle_times_futimens_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        let f = File::create(&path)?;
+
+        let atime = FileTime::from_unix_time(10_000, 0);
+        let mtime = FileTime::from_unix_time(20_000, 0);
+        set_file_handle_times(&f, Some(atime), Some(mtime))?;
+
+        // `None` leaves the time untouched through `UTIME_OMIT`.
+        let new_mtime = FileTime::from_unix_time(30_000, 0);
+        set_file_handle_times(&f, None, Some(new_mtime))?;
+        let metadata = f.metadata()?;
+        assert_eq!(FileTime::from_last_access_time(&metadata), atime);
+        assert_eq!(FileTime::from_last_modification_time(&metadata), new_mtime);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(unix, not(target_os = "redox")))]
+    #[allow(clippy::unnecessary_cast)] // `time_t` is only 32 bits on some platforms
+    fn try_from_file_time_for_libc_test() {
+        use std::convert::TryFrom;
+
+        let ft = FileTime::from_unix_time(10_000, 123_456_789);
+        let ts = libc::timespec::try_from(ft).unwrap();
+        assert_eq!(ts.tv_sec as i64, ft.seconds());
+        assert_eq!(ts.tv_nsec as u32, 123_456_789);
+        let tv = libc::timeval::try_from(ft).unwrap();
+        assert_eq!(tv.tv_sec as i64, ft.seconds());
+        assert_eq!(tv.tv_usec as u32, 123_456);
+
+        let far = FileTime::from_unix_time(i64::from(i32::MAX) + 1, 0);
+        if std::mem::size_of::<libc::time_t>() < 8 {
+            assert!(libc::timespec::try_from(far).is_err());
+            assert!(libc::timeval::try_from(far).is_err());
+        } else {
+            assert!(libc::timespec::try_from(far).is_ok());
+            assert!(libc::timeval::try_from(far).is_ok());
+        }
+    }
+
+    #[test]
+    fn times_match_test() -> io::Result<()> {
+        use super::within_granularity;
+
+        let two_secs = Duration::from_secs(2);
+        let a = FileTime::from_unix_time(10, 0);
+        assert!(within_granularity(
+            &a,
+            &FileTime::from_unix_time(11, 500_000_000),
+            two_secs
+        ));
+        assert!(!within_granularity(
+            &a,
+            &FileTime::from_unix_time(12, 0),
+            two_secs
+        ));
+        assert!(within_granularity(&a, &a, Duration::ZERO));
+        assert!(!within_granularity(
+            &a,
+            &FileTime::from_unix_time(10, 100),
+            Duration::ZERO
+        ));
+
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+        // Microseconds, as with the `force-utimes` feature.
+        let precise = Duration::from_micros(1);
+        let mtime = FileTime::from_unix_time(20_001, 123_456_700);
+        set_file_mtime(&path, mtime)?;
+        assert!(times_match(&path, mtime, precise)?);
+        assert!(!times_match(
+            &path,
+            FileTime::from_unix_time(20_002, 123_456_700),
+            precise
+        )?);
+
+        // A whole second doesn't imply a coarse filesystem.
+        let mtime = FileTime::from_unix_time(10_000, 0);
+        set_file_mtime(&path, mtime)?;
+        assert!(times_match(&path, mtime, precise)?);
+        assert!(!times_match(
+            &path,
+            FileTime::from_unix_time(10_001, 0),
+            precise
+        )?);
+        Ok(())
+    }
+
+    #[test]
+    fn checked_and_saturating_duration_since_test() {
+        let earlier = FileTime::from_unix_time(10, 500_000_000);
+        let later = FileTime::from_unix_time(12, 0);
+        let diff = Duration::from_millis(1_500);
+
+        assert_eq!(later.checked_duration_since(earlier), Some(diff));
+        assert_eq!(later.checked_duration_since(later), Some(Duration::ZERO));
+        assert_eq!(earlier.checked_duration_since(later), None);
+
+        assert_eq!(later.saturating_duration_since(earlier), diff);
+        assert_eq!(later.saturating_duration_since(later), Duration::ZERO);
+        assert_eq!(earlier.saturating_duration_since(later), Duration::ZERO);
+    }
+
+    #[test]
+    fn kernel_supports_nanosecond_times_test() -> io::Result<()> {
+        use super::kernel_supports_nanosecond_times;
+
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+        set_file_times(&path, FileTime::zero(), FileTime::zero())?;
+
+        let supported = kernel_supports_nanosecond_times();
+        cfg_if::cfg_if! {
+            if #[cfg(target_os = "linux")] {
+                assert_eq!(
+                    supported,
+                    !cfg!(feature = "force-utimes")
+                        && super::imp::utimensat_unsupported() == (false, false)
+                );
+            } else if #[cfg(any(windows, all(target_family = "wasm", not(target_os = "emscripten"))))] {
+                assert!(!supported);
+            }
+        }
+
+        // With nanosecond support the time isn't truncated when stored.
+        if supported && !cfg!(emulate_second_only_system) {
+            let mtime = FileTime::from_unix_time(10_000, 123_456_789);
+            set_file_mtime(&path, mtime)?;
+            let metadata = fs::metadata(&path)?;
+            assert_eq!(FileTime::from_last_modification_time(&metadata), mtime);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn le_bytes_test() {
+        for ft in [
+            FileTime::from_unix_time(10_000, 123_456_700),
+            FileTime::from_unix_time(-11_644_473_601, 0),
+            FileTime::zero(),
+        ]
+        .iter()
+        {
+            assert_eq!(FileTime::from_le_bytes(ft.to_le_bytes()), Some(*ft));
+        }
+
+        let bytes = [0x10, 0x27, 0, 0, 0, 0, 0, 0, 0xe8, 0x03, 0, 0];
+        let ft = FileTime::from_le_bytes(bytes).unwrap();
+        assert_eq!(ft.unix_seconds(), 10_000);
+        assert_eq!(ft.nanoseconds(), 1_000);
+        assert_eq!(ft.to_le_bytes(), bytes);
+
+        let bytes = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0];
+        assert_eq!(
+            FileTime::from_le_bytes(bytes),
+            Some(FileTime::from_unix_time(-1, 0))
+        );
+    }
+
+    #[test]
+    fn swap_file_times_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+        let atime = FileTime::from_unix_time(10_000, 0);
+        let mtime = FileTime::from_unix_time(20_000, 0);
+        set_file_times(&path, atime, mtime)?;
+
+        let new_mtime = FileTime::from_unix_time(30_000, 0);
+        assert_eq!(
+            swap_file_times(&path, None, Some(new_mtime))?,
+            (atime, mtime)
+        );
+        let metadata = fs::metadata(&path)?;
+        assert_eq!(FileTime::from_last_access_time(&metadata), atime);
+        assert_eq!(FileTime::from_last_modification_time(&metadata), new_mtime);
+
+        let (old_atime, old_mtime) = swap_file_times(&path, Some(mtime), Some(atime))?;
+        assert_eq!((old_atime, old_mtime), (atime, new_mtime));
+        let metadata = fs::metadata(&path)?;
+        assert_eq!(FileTime::from_last_access_time(&metadata), mtime);
+        assert_eq!(FileTime::from_last_modification_time(&metadata), atime);
+        Ok(())
+    }
+
+    #[test]
+    fn single_time_of_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+        set_file_times(
+            &path,
+            FileTime::from_unix_time(10_000, 0),
+            FileTime::from_unix_time(20_000, 0),
+        )?;
+
+        let metadata = fs::metadata(&path)?;
+        assert_eq!(
+            mtime_of(&path)?,
+            FileTime::from_last_modification_time(&metadata)
+        );
+        assert_eq!(atime_of(&path)?, FileTime::from_last_access_time(&metadata));
+        assert_eq!(btime_of(&path)?, FileTime::from_creation_time(&metadata));
+        assert!(mtime_of(td.path().join("missing")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn now_precise_test() {
+        let coarse = FileTime::now();
+        let precise = FileTime::now_precise();
+        let later = FileTime::now();
+        let system = FileTime::from_system_time(SystemTime::now());
+
+        // Both clocks follow the system time, but the coarse one may lag
+        // behind by up to a clock tick.
+        let tolerance = Duration::from_secs(1);
+        assert!(precise.unix_seconds() > 1_500_000_000);
+        assert!(!precise.is_before(&coarse));
+        assert!(later.saturating_duration_since(precise) < tolerance);
+        assert!(precise.saturating_duration_since(later) < tolerance);
+        assert!(system.saturating_duration_since(precise) < tolerance);
+    }
+
+    #[test]
+    #[cfg_attr(
+        all(target_os = "linux", feature = "force-utimes"),
+        ignore = "requires nanosecond precision"
+    )]
+    fn set_file_atime_verified_test() -> io::Result<()> {
+        use super::{set_file_atime_verified, verify_atime};
+
+        let precise = Duration::from_nanos(100);
+        let atime = FileTime::from_unix_time(10_001, 123_456_700);
+        assert!(verify_atime(atime, atime, precise).is_ok());
+        let one_sec = Duration::from_secs(1);
+        assert!(verify_atime(atime, FileTime::from_unix_time(10_001, 0), one_sec).is_ok());
+        let err = verify_atime(atime, FileTime::from_unix_time(10_001, 0), precise).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+        let err = verify_atime(
+            atime,
+            FileTime::from_unix_time(20_000, 123_456_700),
+            one_sec,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+        set_file_atime_verified(&path, atime, precise)?;
+        let metadata = fs::metadata(&path)?;
+        assert_eq!(FileTime::from_last_access_time(&metadata), atime);
+        Ok(())
+    }
+
+    #[test]
+    fn bump_mtime_above_test() -> io::Result<()> {
+        use super::bump_mtime_above;
+
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+
+        let floor = FileTime::from_unix_time(20_000, 999_999_900);
+        let bumped = bump_mtime_above(&path, floor)?;
+        assert!(bumped.is_after(&floor));
+        let metadata = fs::metadata(&path)?;
+        assert_eq!(FileTime::from_last_modification_time(&metadata), bumped);
+        assert!(bumped.duration_since(&floor).unwrap() <= Duration::from_secs(2));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(target_os = "macos")]
+    fn macos_cached_symbols_test() -> io::Result<()> {
+        use super::imp::supports_nanosecond_times;
+
+        // Every supported macOS release has `utimensat` and `futimens`, and
+        // later lookups come from the cache.
+        assert!(supports_nanosecond_times());
+        assert!(supports_nanosecond_times());
+
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        let f = File::create(&path)?;
+        let mtime = FileTime::from_unix_time(10_000, 123_456_789);
+        set_file_mtime(&path, mtime)?;
+        assert_eq!(FileTime::from_last_modification_time(&f.metadata()?), mtime);
+        let mtime = FileTime::from_unix_time(20_000, 987_654_321);
+        set_file_handle_times(&f, None, Some(mtime))?;
+        assert_eq!(FileTime::from_last_modification_time(&f.metadata()?), mtime);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(unix, not(target_os = "redox")))]
+    fn dir_stamp_test() -> io::Result<()> {
+        use super::DirStamp;
+
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let dir = td.path().join("sandbox");
+        fs::create_dir_all(dir.join("sub"))?;
+        let stamp = DirStamp::open(&dir)?;
+
+        // Files are found relative to the held directory even once it's
+        // moved.
+        let moved = td.path().join("moved");
+        fs::rename(&dir, &moved)?;
+        File::create(moved.join("sub/foo.txt"))?;
+        make_symlink_file("foo.txt", moved.join("sub/bar.txt"))?;
+
+        let atime = FileTime::from_unix_time(10_000, 0);
+        let mtime = FileTime::from_unix_time(20_000, 0);
+        stamp.stamp(Path::new("sub/foo.txt"), atime, mtime, true)?;
+        let metadata = fs::metadata(moved.join("sub/foo.txt"))?;
+        assert_eq!(FileTime::from_last_access_time(&metadata), atime);
+        assert_eq!(FileTime::from_last_modification_time(&metadata), mtime);
+
+        let smtime = FileTime::from_unix_time(30_000, 0);
+        stamp.stamp(Path::new("sub/bar.txt"), atime, smtime, false)?;
+        let metadata = fs::symlink_metadata(moved.join("sub/bar.txt"))?;
+        assert_eq!(FileTime::from_last_modification_time(&metadata), smtime);
+        let metadata = fs::metadata(moved.join("sub/foo.txt"))?;
+        assert_eq!(FileTime::from_last_modification_time(&metadata), mtime);
+        Ok(())
+    }
+
+    #[test]
+    fn debug_test() {
+        let ft = FileTime::from_unix_time(1_609_459_200, 123_456_700);
+        let debug = format!("{:?}", ft);
+        assert!(debug.contains("unix: 1609459200.123456700"), "{}", debug);
+        assert!(
+            debug.contains(&format!("raw_seconds: {}", ft.seconds())),
+            "{}",
+            debug
+        );
+        if cfg!(windows) {
+            assert!(debug.contains("raw_seconds: 13253932800"), "{}", debug);
+        }
+
+        let debug = format!("{:?}", FileTime::from_unix_time(-1, 500_000_000));
+        assert!(debug.contains("unix: -0.500000000"), "{}", debug);
+        let debug = format!("{:?}", FileTime::from_unix_time(-2, 0));
+        assert!(debug.contains("unix: -2.000000000"), "{}", debug);
+    }
+
+    #[test]
+    fn try_set_file_times_skip_unsupported_test() -> io::Result<()> {
+        use super::{imp, try_set_file_times_skip_unsupported};
+
+        let unsupported = io::Error::new(io::ErrorKind::Unsupported, "unsupported");
+        assert!(imp::is_unsupported_error(&unsupported));
+        let not_found = io::Error::from(io::ErrorKind::NotFound);
+        assert!(!imp::is_unsupported_error(&not_found));
+        #[cfg(unix)]
+        for code in [libc::EROFS, libc::EPERM, libc::EOPNOTSUPP].iter() {
+            assert!(imp::is_unsupported_error(&io::Error::from_raw_os_error(
+                *code
+            )));
+        }
+        #[cfg(unix)]
+        assert!(!imp::is_unsupported_error(&io::Error::from_raw_os_error(
+            libc::ENOENT
+        )));
+
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+        let mtime = FileTime::from_unix_time(20_000, 0);
+        assert!(try_set_file_times_skip_unsupported(&path, mtime, mtime)?);
+
+        let missing = td.path().join("missing");
+        let err = try_set_file_times_skip_unsupported(&missing, mtime, mtime).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert!(err.to_string().contains("missing"), "{}", err);
+        Ok(())
+    }
+
+    #[test]
     #[cfg(windows)]
-    fn from_system_time_test() {
-        let time = FileTime::from_system_time(UNIX_EPOCH + Duration::from_secs(10));
-        assert_eq!(11644473610, time.seconds);
-        assert_eq!(0, time.nanos);
+    fn windows_system_time_test() -> io::Result<()> {
+        use windows_sys::Win32::Foundation::SYSTEMTIME;
+
+        let st = SYSTEMTIME {
+            wYear: 2021,
+            wMonth: 1,
+            wDayOfWeek: 5,
+            wDay: 1,
+            wHour: 12,
+            wMinute: 30,
+            wSecond: 15,
+            wMilliseconds: 250,
+        };
+        let ft = FileTime::from_windows_system_time(&st)?;
+        assert_eq!(ft, FileTime::from_unix_time(1_609_504_215, 250_000_000));
+
+        let back = ft.to_windows_system_time()?;
+        assert_eq!(
+            (back.wYear, back.wMonth, back.wDay, back.wDayOfWeek),
+            (2021, 1, 1, 5)
+        );
+        assert_eq!(
+            (back.wHour, back.wMinute, back.wSecond, back.wMilliseconds),
+            (12, 30, 15, 250)
+        );
+
+        let invalid = SYSTEMTIME { wMonth: 13, ..st };
+        assert!(FileTime::from_windows_system_time(&invalid).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn skew_from_now_test() {
+        use std::cmp::Ordering;
+
+        let hour = Duration::from_secs(60 * 60);
+        let now = FileTime::now().unix_seconds();
+
+        let past = FileTime::from_unix_time(now - 3_600, 0);
+        assert!(!past.is_in_future());
+        let (ordering, skew) = past.skew_from_now();
+        assert_eq!(ordering, Ordering::Less);
+        assert!(skew >= hour && skew < hour * 2);
+
+        let future = FileTime::from_unix_time(now + 3_600, 0);
+        assert!(future.is_in_future());
+        let (ordering, skew) = future.skew_from_now();
+        assert_eq!(ordering, Ordering::Greater);
+        assert!(skew > Duration::from_secs(60 * 59) && skew <= hour);
+
+        let (ordering, skew) = FileTime::now().skew_from_now();
+        assert_ne!(ordering, Ordering::Greater);
+        assert!(skew < Duration::from_secs(1));
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn set_file_times_overflow_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        let f = File::create(&path)?;
+        let mtime = FileTime::from_unix_time(20_000, 0);
+        set_file_mtime(&path, mtime)?;
+
+        let extreme = FileTime::from_unix_time(i64::MAX - 11_644_473_600, 0);
+        let err = set_file_mtime(&path, extreme).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let err = set_file_handle_times(&f, None, Some(extreme)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        let metadata = fs::metadata(&path)?;
+        assert_eq!(FileTime::from_last_modification_time(&metadata), mtime);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn windows_dir_creation_time_test() -> io::Result<()> {
+        use std::os::windows::fs::MetadataExt;
+
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo");
+        fs::create_dir(&path)?;
+
+        let metadata = fs::metadata(&path)?;
+        assert!(metadata.is_dir());
+        let btime = FileTime::from_creation_time(&metadata).unwrap();
+        let intervals = metadata.creation_time();
+        assert_eq!(
+            btime,
+            FileTime::from_filetime_raw(intervals as u32, (intervals >> 32) as u32)
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(unix, not(target_os = "redox")))]
+    #[allow(clippy::unnecessary_cast)] // `c_long` is only 32 bits on some platforms
+    fn unix_timespec_parts_test() -> io::Result<()> {
+        use super::{UTIME_NOW, UTIME_OMIT};
+
+        let ft = FileTime::from_unix_time(-5, 123_456_789);
+        assert_eq!(ft.unix_timespec_parts(), (-5, 123_456_789));
+
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "android",
+            target_vendor = "apple",
+            target_os = "freebsd",
+            target_os = "dragonfly",
+            target_os = "netbsd",
+            target_os = "openbsd",
+            target_os = "illumos",
+            target_os = "solaris",
+            target_os = "haiku",
+            target_os = "cygwin"
+        ))]
+        {
+            assert_eq!(UTIME_OMIT, libc::UTIME_OMIT as i64);
+            assert_eq!(UTIME_NOW, libc::UTIME_NOW as i64);
+        }
+        assert_ne!(UTIME_OMIT, UTIME_NOW);
+
+        // Assembling a `timespec` by hand which only changes the mtime.
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        let f = File::create(&path)?;
+        let atime = FileTime::from_unix_time(10_000, 0);
+        set_file_handle_times(&f, Some(atime), None)?;
+
+        let mtime = FileTime::from_unix_time(20_000, 500);
+        let (sec, nsec) = mtime.unix_timespec_parts();
+        let mut times: [libc::timespec; 2] = unsafe { std::mem::zeroed() };
+        times[0].tv_nsec = UTIME_OMIT as _;
+        times[1].tv_sec = sec as _;
+        times[1].tv_nsec = nsec as _;
+        use std::os::unix::prelude::*;
+        let rc = unsafe { libc::futimens(f.as_raw_fd(), times.as_ptr()) };
+        assert_eq!(rc, 0, "{}", io::Error::last_os_error());
+
+        let metadata = f.metadata()?;
+        assert_eq!(FileTime::from_last_access_time(&metadata), atime);
+        assert_eq!(FileTime::from_last_modification_time(&metadata), mtime);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn linux_creation_time_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+
+        // Whether this is available depends on the kernel, libc and
+        // filesystem, but either outcome must be handled gracefully.
+        let metadata = fs::metadata(&path)?;
+        match FileTime::from_creation_time(&metadata) {
+            Some(btime) => {
+                assert!(super::supports_creation_time());
+                assert!(!btime.is_after(&FileTime::now()));
+                assert_eq!(btime_of(&path)?, Some(btime));
+            }
+            None => assert!(metadata.created().is_err()),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn can_set_ctime_test() -> io::Result<()> {
+        use super::can_set_ctime;
+
+        assert!(!can_set_ctime());
+
+        // Setting other times bumps the status change time on Unix.
+        #[cfg(all(unix, not(target_os = "redox")))]
+        {
+            use std::os::unix::fs::MetadataExt;
+
+            let td = Builder::new().prefix("filetime").tempdir()?;
+            let path = td.path().join("foo.txt");
+            File::create(&path)?;
+            let before = FileTime::now();
+            let past = FileTime::from_unix_time(10_000, 0);
+            set_file_times(&path, past, past)?;
+            let metadata = fs::metadata(&path)?;
+            // The kernel's clock is coarse, so allow it to lag slightly.
+            let ctime = FileTime::from_unix_time(metadata.ctime() + 1, 0);
+            assert!(ctime.is_after(&before));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn from_rfc3339_test() {
+        let parse = FileTime::from_rfc3339;
+        assert_eq!(
+            parse("2021-05-01T12:00:00Z").unwrap(),
+            FileTime::from_unix_time(1_619_870_400, 0)
+        );
+        assert_eq!(
+            parse("1970-01-01T00:00:00.123456789Z").unwrap(),
+            FileTime::from_unix_time(0, 123_456_789)
+        );
+        assert_eq!(
+            parse("2000-02-29t23:59:59.5z").unwrap(),
+            FileTime::from_unix_time(951_868_799, 500_000_000)
+        );
+        assert_eq!(
+            parse("1969-12-31T23:59:59Z").unwrap(),
+            FileTime::from_unix_time(-1, 0)
+        );
+
+        for invalid in [
+            "",
+            "2021-05-01",
+            "2021-05-01T12:00:00",
+            "2021-05-01T12:00:00+02:00",
+            "2021-05-01 12:00:00Z",
+            "2021-13-01T12:00:00Z",
+            "2021-02-29T12:00:00Z",
+            "2021-05-01T24:00:00Z",
+            "2021-05-01T12:00:60Z",
+            "2021-05-01T12:00:00.Z",
+            "2021-05-01T12:00:00.1234567890Z",
+            "2021-05-01T12:00:0xZ",
+            "+021-05-01T12:00:00Z",
+        ]
+        .iter()
+        {
+            let err = parse(invalid).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn update_file_times_test() -> io::Result<()> {
+        use super::update_file_times;
+
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+        let atime = FileTime::from_unix_time(10_000, 0);
+        let mtime = FileTime::from_unix_time(20_000, 0);
+        set_file_times(&path, atime, mtime)?;
+
+        let at_least = |floor: FileTime| {
+            move |meta: &fs::Metadata| {
+                let current = FileTime::from_last_modification_time(meta);
+                (None, FileTime::max_of(vec![current, floor]))
+            }
+        };
+
+        update_file_times(&path, at_least(FileTime::from_unix_time(15_000, 0)))?;
+        assert_eq!(mtime_of(&path)?, mtime);
+
+        let floor = FileTime::from_unix_time(25_000, 0);
+        update_file_times(&path, at_least(floor))?;
+        assert_eq!(mtime_of(&path)?, floor);
+        assert_eq!(atime_of(&path)?, atime);
+        Ok(())
+    }
+
+    #[test]
+    fn try_from_nanos_since_1970_test() {
+        let ft = FileTime::try_from_nanos_since_1970(1_500_000_000_123_456_700).unwrap();
+        assert_eq!(ft, FileTime::from_unix_time(1_500_000_000, 123_456_700));
+        let ft = FileTime::try_from_nanos_since_1970(-100).unwrap();
+        assert_eq!(ft, FileTime::from_unix_time(-1, 999_999_900));
+
+        let err = FileTime::try_from_nanos_since_1970(i128::MAX).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let err = FileTime::try_from_nanos_since_1970(i128::MIN).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn set_times_with_flags_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+        let spath = td.path().join("bar.txt");
+        make_symlink_file(&path, &spath)?;
+        let atime = FileTime::from_unix_time(10_000, 0);
 
-        let time = FileTime::from_system_time(UNIX_EPOCH - Duration::from_secs(10));
-        assert_eq!(11644473590, time.seconds);
-        assert_eq!(0, time.nanos);
+        let follow_mtime = FileTime::from_unix_time(20_000, 0);
+        set_times_with_flags(&spath, atime, follow_mtime, SymlinkFlag::Follow)?;
+        assert_eq!(mtime_of(&path)?, follow_mtime);
+        let metadata = fs::symlink_metadata(&spath)?;
+        assert_ne!(
+            FileTime::from_last_modification_time(&metadata),
+            follow_mtime
+        );
 
-        let time = FileTime::from_system_time(UNIX_EPOCH - Duration::from_millis(1100));
-        assert_eq!(11644473598, time.seconds);
-        assert_eq!(900_000_000, time.nanos);
+        let nofollow_mtime = FileTime::from_unix_time(30_000, 0);
+        set_times_with_flags(&spath, atime, nofollow_mtime, SymlinkFlag::NoFollow)?;
+        assert_eq!(mtime_of(&path)?, follow_mtime);
+        let metadata = fs::symlink_metadata(&spath)?;
+        assert_eq!(
+            FileTime::from_last_modification_time(&metadata),
+            nofollow_mtime
+        );
+        Ok(())
+    }
 
-        let time = FileTime::from_system_time(UNIX_EPOCH - Duration::from_secs(12_000_000_000));
-        assert_eq!(-355526400, time.seconds);
-        assert_eq!(0, time.nanos);
+    #[test]
+    fn to_utc_components_test() {
+        let ft = FileTime::from_unix_time(0, 0);
+        assert_eq!(ft.to_utc_components(), (1970, 1, 1, 0, 0, 0, 0));
+
+        // A leap day.
+        let ft = FileTime::from_unix_time(1_709_210_096, 500_000_000);
+        assert_eq!(
+            ft.to_utc_components(),
+            (2024, 2, 29, 12, 34, 56, 500_000_000)
+        );
+
+        // Before the epoch, and the century rules for leap years.
+        let ft = FileTime::from_unix_time(-1, 0);
+        assert_eq!(ft.to_utc_components(), (1969, 12, 31, 23, 59, 59, 0));
+        let ft = FileTime::from_unix_time(-2_203_891_200, 0);
+        assert_eq!(ft.to_utc_components(), (1900, 3, 1, 0, 0, 0, 0));
+        let ft = FileTime::from_unix_time(951_782_400, 0);
+        assert_eq!(ft.to_utc_components(), (2000, 2, 29, 0, 0, 0, 0));
+
+        let ft = FileTime::from_rfc3339("1601-01-01T00:00:00Z").unwrap();
+        assert_eq!(ft.to_utc_components(), (1601, 1, 1, 0, 0, 0, 0));
+
+        // Years beyond the range of an `i32`.
+        if !cfg!(windows) {
+            let ft = FileTime::from_unix_time(i64::MAX, 0);
+            assert_eq!(
+                ft.to_utc_components(),
+                (292_277_026_596, 12, 4, 15, 30, 7, 0)
+            );
+            let ft = FileTime::from_unix_time(i64::MIN, 0);
+            assert_eq!(
+                ft.to_utc_components(),
+                (-292_277_022_657, 1, 27, 8, 29, 52, 0)
+            );
+        }
     }
 
     #[test]
-    #[cfg(not(windows))]
-    fn from_system_time_test() {
-        let time = FileTime::from_system_time(UNIX_EPOCH + Duration::from_secs(10));
-        assert_eq!(10, time.seconds);
-        assert_eq!(0, time.nanos);
+    #[cfg(all(target_os = "linux", feature = "force-utimes"))]
+    fn force_utimes_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        let f = File::create(&path)?;
 
-        let time = FileTime::from_system_time(UNIX_EPOCH - Duration::from_secs(10));
-        assert_eq!(-10, time.seconds);
-        assert_eq!(0, time.nanos);
+        let time = FileTime::from_unix_time(10_000, 123_456_789);
+        set_file_times(&path, time, time)?;
+        assert_eq!(mtime_of(&path)?.nanoseconds() % 1000, 0);
+        assert_eq!(atime_of(&path)?.nanoseconds() % 1000, 0);
 
-        let time = FileTime::from_system_time(UNIX_EPOCH - Duration::from_millis(1100));
-        assert_eq!(-2, time.seconds);
-        assert_eq!(900_000_000, time.nanos);
+        set_file_handle_times(&f, Some(time), Some(time))?;
+        assert_eq!(mtime_of(&path)?.nanoseconds() % 1000, 0);
 
-        let time = FileTime::from_system_time(UNIX_EPOCH - Duration::from_secs(12_000_000));
-        assert_eq!(-12_000_000, time.seconds);
-        assert_eq!(0, time.nanos);
+        set_file_mtime(&path, time)?;
+        assert_eq!(
+            mtime_of(&path)?,
+            FileTime::from_unix_time(10_000, 123_456_000)
+        );
+
+        // Relative paths go through `/proc/self/fd` rather than `utimensat`.
+        let stamp = super::DirStamp::open(td.path())?;
+        let time = FileTime::from_unix_time(20_000, 987_654_321);
+        stamp.stamp(Path::new("foo.txt"), time, time, true)?;
+        assert_eq!(
+            mtime_of(&path)?,
+            FileTime::from_unix_time(20_000, 987_654_000)
+        );
+        Ok(())
     }
 
     #[test]
-    fn set_file_times_test() -> io::Result<()> {
+    fn from_metadata_tagged_test() -> io::Result<()> {
         let td = Builder::new().prefix("filetime").tempdir()?;
         let path = td.path().join("foo.txt");
-        let mut f = File::create(&path)?;
+        File::create(&path)?;
+        let atime = FileTime::from_unix_time(10_000, 0);
+        let mtime = FileTime::from_unix_time(20_000, 0);
+        set_file_times(&path, atime, mtime)?;
 
-        let metadata = fs::metadata(&path)?;
-        let mtime = FileTime::from_last_modification_time(&metadata);
-        let atime = FileTime::from_last_access_time(&metadata);
+        let meta = fs::metadata(&path)?;
+        let tagged = from_metadata_tagged(&meta);
+        assert_eq!(tagged.atime.time, atime);
+        assert_eq!(tagged.atime.source, TimeSource::Accessed);
+        assert_eq!(tagged.mtime.time, mtime);
+        assert_eq!(tagged.mtime.source, TimeSource::Modified);
+        let btime = tagged.btime.map(|t| (t.time, t.source));
+        let expected = FileTime::from_creation_time(&meta).map(|t| (t, TimeSource::Created));
+        assert_eq!(btime, expected);
+
+        let constructed = TaggedTime::from(atime);
+        assert_eq!(constructed.source, TimeSource::Constructed);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn windows_out_of_range_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        let f = File::create(&path)?;
+
+        let before_1601 = FileTime::from_unix_time(-11_644_473_601, 0);
+        let err = set_file_times(&path, before_1601, before_1601).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(err.to_string(), "timestamp out of valid Windows range");
+        let err = set_file_handle_times(&f, None, Some(before_1601)).unwrap_err();
+        assert_eq!(err.to_string(), "timestamp out of valid Windows range");
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "std-file-times")]
+    fn std_file_times_test() -> io::Result<()> {
+        use std::convert::{TryFrom, TryInto};
+
+        let atime = FileTime::from_unix_time(10_000, 0);
+        let mtime = FileTime::from_unix_time(-20_000, 500_000_000);
+
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        let f = File::create(&path)?;
+        f.set_times(SetTimes::new().atime(atime).mtime(mtime).try_into()?)?;
+        assert_eq!(atime_of(&path)?, atime);
+        assert_eq!(mtime_of(&path)?, mtime);
+
+        // Times which aren't selected are left unchanged.
+        let new_mtime = FileTime::from_unix_time(30_000, 0);
+        f.set_times(SetTimes::new().mtime(new_mtime).try_into()?)?;
+        assert_eq!(atime_of(&path)?, atime);
+        assert_eq!(mtime_of(&path)?, new_mtime);
+
+        let latest = FileTime::from_unix_time(i64::MAX - 11_644_473_600, 0);
+        let result = fs::FileTimes::try_from(SetTimes::new().mtime(latest));
+        if cfg!(windows) {
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        } else {
+            assert!(result.is_ok());
+        }
+
+        let result = fs::FileTimes::try_from(SetTimes::new().crtime(atime));
+        assert_eq!(result.is_ok(), cfg!(windows));
+        Ok(())
+    }
+
+    #[test]
+    fn into_system_time_test() {
+        use std::convert::{TryFrom, TryInto};
+
+        let time = FileTime::from_unix_time(10_000, 500_000_000);
+        assert_eq!(
+            SystemTime::try_from(time).unwrap(),
+            UNIX_EPOCH + Duration::new(10_000, 500_000_000)
+        );
+        let time = FileTime::from_unix_time(-20_000, 500_000_000);
+        assert_eq!(
+            SystemTime::try_from(time).unwrap(),
+            UNIX_EPOCH - Duration::new(19_999, 500_000_000)
+        );
+        assert_eq!(FileTime::from_system_time(time.try_into().unwrap()), time);
+
+        let max = FileTime {
+            seconds: i64::MAX,
+            nanos: 999_999_999,
+        };
+        // Unix's `SystemTime` covers every `FileTime`, but Windows' doesn't.
+        let result = SystemTime::try_from(max);
+        if cfg!(windows) {
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        } else {
+            assert!(result.is_ok());
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "redox")]
+    fn redox_set_file_mtime_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+        let atime = FileTime::from_unix_time(10_000, 123_456_789);
+        let mtime = FileTime::from_unix_time(20_000, 0);
         set_file_times(&path, atime, mtime)?;
 
-        let new_mtime = FileTime::from_unix_time(10_000, 0);
-        set_file_times(&path, atime, new_mtime)?;
+        let new_mtime = FileTime::from_unix_time(30_000, 987_654_321);
+        set_file_mtime(&path, new_mtime)?;
+        assert_eq!(mtime_of(&path)?, new_mtime);
+        assert_eq!(atime_of(&path)?, atime);
+        Ok(())
+    }
 
-        let metadata = fs::metadata(&path)?;
-        let mtime = FileTime::from_last_modification_time(&metadata);
-        assert_eq!(mtime, new_mtime, "modification should be updated");
+    struct MockTimes {
+        mtime: (i64, u32),
+        atime: (i64, u32),
+        btime: Option<(i64, u32)>,
+    }
 
-        // Update just mtime
-        let new_mtime = FileTime::from_unix_time(20_000, 0);
-        set_file_handle_times(&mut f, None, Some(new_mtime))?;
-        let metadata = f.metadata()?;
-        let mtime = FileTime::from_last_modification_time(&metadata);
-        assert_eq!(mtime, new_mtime, "modification time should be updated");
-        let new_atime = FileTime::from_last_access_time(&metadata);
-        assert_eq!(atime, new_atime, "accessed time should not be updated");
+    impl RawTimes for MockTimes {
+        fn mtime_parts(&self) -> (i64, u32) {
+            self.mtime
+        }
 
-        // Update just atime
-        let new_atime = FileTime::from_unix_time(30_000, 0);
-        set_file_handle_times(&mut f, Some(new_atime), None)?;
-        let metadata = f.metadata()?;
-        let mtime = FileTime::from_last_modification_time(&metadata);
-        assert_eq!(mtime, new_mtime, "modification time should not be updated");
-        let atime = FileTime::from_last_access_time(&metadata);
-        assert_eq!(atime, new_atime, "accessed time should be updated");
+        fn atime_parts(&self) -> (i64, u32) {
+            self.atime
+        }
 
-        let spath = td.path().join("bar.txt");
-        make_symlink_file(&path, &spath)?;
-        let metadata = fs::symlink_metadata(&spath)?;
-        let smtime = FileTime::from_last_modification_time(&metadata);
+        fn btime_parts(&self) -> Option<(i64, u32)> {
+            self.btime
+        }
+    }
 
-        set_file_times(&spath, atime, mtime)?;
+    #[test]
+    fn raw_times_mock_test() {
+        let mock = MockTimes {
+            mtime: (-10_000, 500_000_000),
+            atime: (20_000, 0),
+            btime: None,
+        };
+        assert_eq!(
+            FileTime::from_last_modification_time(&mock),
+            FileTime::from_unix_time(-10_000, 500_000_000)
+        );
+        assert_eq!(
+            FileTime::from_last_access_time(&mock),
+            FileTime::from_unix_time(20_000, 0)
+        );
+        assert_eq!(FileTime::from_creation_time(&mock), None);
 
-        let metadata = fs::metadata(&path)?;
-        let cur_mtime = FileTime::from_last_modification_time(&metadata);
-        assert_eq!(mtime, cur_mtime);
+        let mock = MockTimes {
+            btime: Some((-1, 999_999_900)),
+            ..mock
+        };
+        assert_eq!(
+            FileTime::from_creation_time(&mock),
+            Some(FileTime::from_unix_time(-1, 999_999_900))
+        );
+        assert_eq!(
+            FileTime::from_creation_time(&mock).unwrap().unix_seconds(),
+            -1
+        );
+    }
 
-        let metadata = fs::symlink_metadata(&spath)?;
-        let cur_mtime = FileTime::from_last_modification_time(&metadata);
-        assert_eq!(smtime, cur_mtime);
+    #[test]
+    fn nanos_normalized_test() {
+        let ft = FileTime::from_unix_time(10, 2_500_000_000);
+        assert_eq!(ft, FileTime::from_unix_time(12, 500_000_000));
+        assert_eq!(ft.nanoseconds(), 500_000_000);
+    }
 
-        set_file_times(&spath, atime, new_mtime)?;
+    #[test]
+    #[cfg(all(unix, not(target_os = "redox")))]
+    #[allow(clippy::unnecessary_cast)] // `c_long` is only 32 bits on some platforms
+    fn utime_omit_normalized_test() -> io::Result<()> {
+        use super::UTIME_OMIT;
 
-        let metadata = fs::metadata(&path)?;
-        let mtime = FileTime::from_last_modification_time(&metadata);
-        assert_eq!(mtime, new_mtime);
+        let ft = FileTime::from_unix_time(10_000, UTIME_OMIT as u32);
+        let expected = FileTime::from_unix_time(
+            10_000 + UTIME_OMIT / 1_000_000_000,
+            (UTIME_OMIT % 1_000_000_000) as u32,
+        );
+        assert_eq!(ft, expected);
+        assert!(ft.nanoseconds() < 1_000_000_000);
+
+        // A raw `timespec` holding `UTIME_OMIT` is normalized in the same way.
+        let mut ts: libc::timespec = unsafe { std::mem::zeroed() };
+        ts.tv_sec = 10_000;
+        ts.tv_nsec = UTIME_OMIT as _;
+        let raw = FileTime::from_timespec(ts);
+        assert!(raw.nanoseconds() < 1_000_000_000);
+        assert_eq!(
+            raw.unix_nanos(),
+            10_000 * 1_000_000_000 + i128::from(UTIME_OMIT)
+        );
+
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+
+        // The normalized times are actually written rather than omitted.
+        set_file_times(&path, ft, expected)?;
+        assert_eq!(mtime_of(&path)?.seconds(), expected.seconds());
+        set_file_times(&path, raw, raw)?;
+        assert_eq!(mtime_of(&path)?.seconds(), raw.seconds());
+        Ok(())
+    }
+
+    #[test]
+    fn from_unix_time_saturates_test() {
+        let ft = FileTime::from_unix_time(i64::MAX - 100_000_000_000, 4_000_000_000);
+        assert_eq!(ft.unix_seconds(), i64::MAX - 100_000_000_000 + 4);
+        let ft = FileTime::from_unix_time(i64::MIN, 4_000_000_000);
+        assert_eq!(ft.unix_seconds(), i64::MIN + 4);
+    }
+
+    #[test]
+    fn copy_file_mtime_and_atime_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let src = td.path().join("src.txt");
+        let dst = td.path().join("dst.txt");
+        File::create(&src)?;
+        File::create(&dst)?;
+        let src_atime = FileTime::from_unix_time(10_000, 0);
+        let src_mtime = FileTime::from_unix_time(20_000, 0);
+        set_file_times(&src, src_atime, src_mtime)?;
+        let dst_atime = FileTime::from_unix_time(30_000, 0);
+        let dst_mtime = FileTime::from_unix_time(40_000, 0);
+        set_file_times(&dst, dst_atime, dst_mtime)?;
+
+        copy_file_mtime(&src, &dst)?;
+        assert_eq!(mtime_of(&dst)?, src_mtime);
+        assert_eq!(atime_of(&dst)?, dst_atime);
+
+        copy_file_atime(&src, &dst)?;
+        assert_eq!(atime_of(&dst)?, src_atime);
+        assert_eq!(mtime_of(&dst)?, src_mtime);
+        Ok(())
+    }
+
+    #[test]
+    fn is_zero_test() {
+        assert!(FileTime::zero().is_zero());
+        assert!(!FileTime::from_unix_time(0, 1_000).is_zero());
+        assert!(!FileTime::zero().with_seconds(1).is_zero());
+
+        let unix_epoch = FileTime::from_unix_time(0, 0);
+        if cfg!(windows) {
+            assert!(!unix_epoch.is_zero());
+            assert!(FileTime::from_unix_time(-11_644_473_600, 0).is_zero());
+        } else {
+            assert!(unix_epoch.is_zero());
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    #[cfg_attr(feature = "force-utimes", ignore = "requires nanosecond precision")]
+    fn set_file_times_opath_test() -> io::Result<()> {
+        use super::set_file_times_opath;
+        use std::os::unix::fs::PermissionsExt;
+
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+        // Neither reading nor writing the file is needed.
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o000))?;
+
+        let atime = FileTime::from_unix_time(10_000, 123_456_789);
+        let mtime = FileTime::from_unix_time(20_000, 987_654_321);
+        match set_file_times_opath(&path, atime, mtime) {
+            // `AT_EMPTY_PATH` isn't supported by `utimensat` before Linux 5.8.
+            Err(e) if e.raw_os_error() == Some(libc::EINVAL) => return Ok(()),
+            other => other?,
+        }
+        assert_eq!(atime_of(&path)?, atime);
+        assert_eq!(mtime_of(&path)?, mtime);
+        Ok(())
+    }
+
+    #[test]
+    fn older_than_test() {
+        let hour = Duration::from_secs(3600);
+        let now = FileTime::now();
+        let old = FileTime::from_unix_time(now.unix_seconds() - 7200, 0);
+        assert!(old.older_than(hour));
+        assert!(!old.older_than(Duration::from_secs(86_400)));
+
+        let recent = FileTime::from_unix_time(now.unix_seconds() - 60, 0);
+        assert!(!recent.older_than(hour));
+        let future = FileTime::from_unix_time(now.unix_seconds() + 7200, 0);
+        assert!(!future.older_than(Duration::ZERO));
+    }
+
+    #[test]
+    fn stamp_on_close_test() -> io::Result<()> {
+        use std::io::Write;
+
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        let f = File::create(&path)?;
+        let atime = FileTime::from_unix_time(10_000, 0);
+        let mtime = FileTime::from_unix_time(20_000, 0);
+
+        let stamp = StampOnClose::new(&f, Some(atime), Some(mtime));
+        (&f).write_all(b"contents")?;
+        stamp.finish()?;
+        assert_eq!(mtime_of(&path)?, mtime);
+
+        let mtime = FileTime::from_unix_time(30_000, 0);
+        {
+            let _stamp = StampOnClose::new(&f, None, Some(mtime));
+        }
+        assert_eq!(mtime_of(&path)?, mtime);
+        assert_eq!(atime_of(&path)?, atime);
+        Ok(())
+    }
+
+    #[test]
+    fn raw_times_out_of_range_nanos_test() {
+        let mock = MockTimes {
+            mtime: (10_000, 1_500_000_000),
+            atime: (10_000, u32::MAX),
+            btime: Some((-10_000, 1_000_000_000)),
+        };
+        let clamped = |seconds| FileTime::from_unix_time(seconds, 999_999_999);
+        assert_eq!(
+            FileTime::from_last_modification_time(&mock),
+            clamped(10_000)
+        );
+        assert_eq!(FileTime::from_last_access_time(&mock), clamped(10_000));
+        assert_eq!(FileTime::from_creation_time(&mock), Some(clamped(-10_000)));
+    }
+
+    #[test]
+    fn set_file_times_clamped_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+
+        let atime = UNIX_EPOCH + Duration::from_secs(10_000);
+        let mtime = UNIX_EPOCH + Duration::from_secs(20_000);
+        let report = set_file_times_clamped(&path, atime, mtime)?;
+        assert!(!report.is_clamped());
+        assert_eq!(report.atime(), FileTime::from_unix_time(10_000, 0));
+        assert_eq!(mtime_of(&path)?, FileTime::from_unix_time(20_000, 0));
+
+        let min = FileTime::from_unix_time(0, 0);
+        let max = FileTime::from_unix_time(100, 0);
+        let early = FileTime::from_unix_time(-1, 0);
+        let late = FileTime::from_unix_time(100, 1_000);
+        assert_eq!(super::clamp_to_range(early, min, max), (min, true));
+        assert_eq!(super::clamp_to_range(late, min, max), (max, true));
+        assert_eq!(super::clamp_to_range(max, min, max), (max, false));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn set_file_times_clamped_far_future_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+
+        let atime = UNIX_EPOCH + Duration::from_secs(10_000);
+        let far_future = UNIX_EPOCH + Duration::from_secs(1_500_000_000_000);
+        let report = set_file_times_clamped(&path, atime, far_future)?;
+        assert!(!report.atime_clamped());
+        assert!(report.mtime_clamped());
+        assert!(report.mtime() < FileTime::from_system_time(far_future));
+        Ok(())
+    }
+
+    #[test]
+    fn handle_times_test() -> io::Result<()> {
+        use std::io::Write;
+
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        let mut f = File::create(&path)?;
+        f.write_all(b"contents")?;
+        let atime = FileTime::from_unix_time(10_000, 0);
+        let mtime = FileTime::from_unix_time(20_000, 0);
+        set_file_handle_times(&f, Some(atime), Some(mtime))?;
+
+        let times = handle_times(&f)?;
+        assert_eq!(times, get_file_times(&path)?);
+        assert_eq!((times.0, times.1), (atime, mtime));
+
+        // The handle still refers to the same file after it's renamed.
+        let renamed = td.path().join("bar.txt");
+        fs::rename(&path, &renamed)?;
+        assert_eq!(handle_times(&f)?.1, mtime);
+        Ok(())
+    }
+
+    #[test]
+    fn try_from_system_time_test() {
+        let recent = UNIX_EPOCH + Duration::from_secs(10_000);
+        assert_eq!(
+            FileTime::try_from_system_time(recent).unwrap(),
+            FileTime::from_unix_time(10_000, 0)
+        );
+
+        // 1500-01-01T00:00:00Z
+        let year_1500 = UNIX_EPOCH - Duration::from_secs(14_831_769_600);
+        let result = FileTime::try_from_system_time(year_1500);
+        cfg_if::cfg_if! {
+            if #[cfg(windows)] {
+                assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+            } else {
+                let ft = result.unwrap();
+                assert_eq!(ft.unix_seconds(), -14_831_769_600);
+                assert_eq!(ft.to_utc_components(), (1500, 1, 1, 0, 0, 0, 0));
+            }
+        }
+    }
+
+    #[test]
+    fn normalize_for_comparison_test() {
+        let ext4 = FileTime::from_unix_time(10_001, 500_000_000);
+        let fat = FileTime::from_unix_time(10_000, 0);
+        let ns = Duration::from_nanos(1);
+        let two_secs = Duration::from_secs(2);
+        assert_eq!(
+            normalize_for_comparison(ext4, ns, fat, two_secs),
+            (fat, fat)
+        );
+        assert_eq!(
+            normalize_for_comparison(fat, two_secs, ext4, ns),
+            (fat, fat)
+        );
+
+        // Before the epoch times are still truncated towards the past.
+        let before = FileTime::from_unix_time(-3, 500_000_000);
+        let (a, b) = normalize_for_comparison(before, Duration::from_secs(1), fat, two_secs);
+        assert_eq!(a, FileTime::from_unix_time(-4, 0));
+        assert_eq!(b, fat);
+
+        let (a, b) = normalize_for_comparison(ext4, Duration::ZERO, ext4, Duration::ZERO);
+        assert_eq!((a, b), (ext4, ext4));
+        let ms = Duration::from_millis(1);
+        let (a, _) = normalize_for_comparison(ext4, ms, ext4, Duration::from_micros(1));
+        assert_eq!(a, ext4);
+    }
+
+    #[test]
+    fn update_file_times_all_or_nothing_test() -> io::Result<()> {
+        use super::update_file_times;
+
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+        let atime = FileTime::from_unix_time(10_000, 0);
+        let mtime = FileTime::from_unix_time(20_000, 0);
+        set_file_times(&path, atime, mtime)?;
 
-        let metadata = fs::symlink_metadata(&spath)?;
-        let mtime = FileTime::from_last_modification_time(&metadata);
-        assert_eq!(mtime, smtime);
+        // Not reachable through the public constructors, so the write fails.
+        let malformed = FileTime {
+            seconds: 30_000,
+            nanos: 2_000_000_000,
+        };
+        let new_atime = FileTime::from_unix_time(40_000, 0);
+        let err = update_file_times(&path, |_| (Some(new_atime), Some(malformed))).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(atime_of(&path)?, atime);
+        assert_eq!(mtime_of(&path)?, mtime);
+
+        let err = swap_file_times(&path, None, Some(malformed)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(mtime_of(&path)?, mtime);
         Ok(())
     }
 
     #[test]
-    fn set_dir_times_test() -> io::Result<()> {
+    #[cfg(target_os = "cygwin")]
+    fn cygwin_set_and_read_test() -> io::Result<()> {
         let td = Builder::new().prefix("filetime").tempdir()?;
-        let path = td.path().join("foo");
-        fs::create_dir(&path)?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
 
-        let metadata = fs::metadata(&path)?;
-        let mtime = FileTime::from_last_modification_time(&metadata);
-        let atime = FileTime::from_last_access_time(&metadata);
+        // Cygwin stores times in a Windows `FILETIME`, so use 100ns multiples.
+        let atime = FileTime::from_unix_time(10_000, 123_456_700);
+        let mtime = FileTime::from_unix_time(20_000, 987_654_300);
         set_file_times(&path, atime, mtime)?;
+        assert_eq!(atime_of(&path)?, atime);
+        assert_eq!(mtime_of(&path)?, mtime);
 
-        let new_mtime = FileTime::from_unix_time(10_000, 0);
-        set_file_times(&path, atime, new_mtime)?;
-
-        let metadata = fs::metadata(&path)?;
-        let mtime = FileTime::from_last_modification_time(&metadata);
-        assert_eq!(mtime, new_mtime, "modification should be updated");
+        set_file_mtime(&path, atime)?;
+        assert_eq!(get_file_times(&path)?.1, atime);
+        Ok(())
+    }
 
-        // Update just mtime
-        let new_mtime = FileTime::from_unix_time(20_000, 0);
-        set_file_mtime(&path, new_mtime)?;
-        let metadata = fs::metadata(&path)?;
-        let mtime = FileTime::from_last_modification_time(&metadata);
-        assert_eq!(mtime, new_mtime, "modification time should be updated");
-        let new_atime = FileTime::from_last_access_time(&metadata);
-        assert_eq!(atime, new_atime, "accessed time should not be updated");
+    #[test]
+    fn add_seconds_test() {
+        let ft = FileTime::from_unix_time(10, 500_000_000);
+        assert_eq!(
+            ft.add_seconds(3600),
+            FileTime::from_unix_time(3610, 500_000_000)
+        );
+        assert_eq!(
+            ft.add_seconds(-3600),
+            FileTime::from_unix_time(-3590, 500_000_000)
+        );
+        assert_eq!(ft.add_seconds(-10).unix_seconds(), 0);
+        assert_eq!(ft.add_seconds(0), ft);
 
-        // Update just atime
-        let new_atime = FileTime::from_unix_time(30_000, 0);
-        set_file_atime(&path, new_atime)?;
-        let metadata = fs::metadata(&path)?;
-        let mtime = FileTime::from_last_modification_time(&metadata);
-        assert_eq!(mtime, new_mtime, "modification time should not be updated");
-        let atime = FileTime::from_last_access_time(&metadata);
-        assert_eq!(atime, new_atime, "accessed time should be updated");
+        assert_eq!(
+            ft.checked_add_seconds(-20),
+            Some(FileTime::from_unix_time(-10, 500_000_000))
+        );
+        assert_eq!(ft.checked_add_seconds(i64::MAX), None);
+    }
 
-        let spath = td.path().join("bar");
-        make_symlink_dir(&path, &spath)?;
-        let metadata = fs::symlink_metadata(&spath)?;
-        let smtime = FileTime::from_last_modification_time(&metadata);
+    #[test]
+    fn newest_mtime_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        assert_eq!(newest_mtime(td.path())?, None);
 
-        set_file_times(&spath, atime, mtime)?;
+        let sub = td.path().join("sub");
+        fs::create_dir(&sub)?;
+        let a = td.path().join("a.txt");
+        let b = sub.join("b.txt");
+        File::create(&a)?;
+        File::create(&b)?;
+        let old = FileTime::from_unix_time(10_000, 0);
+        let newest = FileTime::from_unix_time(30_000, 0);
+        set_file_times(&a, old, FileTime::from_unix_time(20_000, 0))?;
+        set_file_times(&b, old, newest)?;
 
-        let metadata = fs::metadata(&path)?;
-        let cur_mtime = FileTime::from_last_modification_time(&metadata);
-        assert_eq!(mtime, cur_mtime);
+        // A symlink to the tree itself isn't followed.
+        let link = sub.join("link");
+        make_symlink_dir(td.path(), &link)?;
+        set_symlink_file_times(&link, old, old)?;
+        set_file_times(&sub, old, old)?;
 
-        let metadata = fs::symlink_metadata(&spath)?;
-        let cur_mtime = FileTime::from_last_modification_time(&metadata);
-        assert_eq!(smtime, cur_mtime);
+        assert_eq!(newest_mtime(td.path())?, Some(newest));
+        Ok(())
+    }
 
-        set_file_times(&spath, atime, new_mtime)?;
+    #[test]
+    fn try_normalize_test() {
+        let ft = FileTime::from_unix_time(1_500_000_000, 123_456_789);
+        assert_eq!(ft.try_normalize().unwrap(), ft);
 
-        let metadata = fs::metadata(&path)?;
-        let mtime = FileTime::from_last_modification_time(&metadata);
-        assert_eq!(mtime, new_mtime);
+        let over_nanos = FileTime {
+            seconds: 0,
+            nanos: 1_000_000_000,
+        };
+        let err = over_nanos.try_normalize().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
 
-        let metadata = fs::symlink_metadata(&spath)?;
-        let mtime = FileTime::from_last_modification_time(&metadata);
-        assert_eq!(mtime, smtime);
-        Ok(())
+        let pre_1601 = FileTime::from_unix_time(-12_000_000_000, 0);
+        assert_eq!(pre_1601.try_normalize().is_err(), cfg!(windows));
     }
 
     #[test]
-    fn set_file_times_pre_unix_epoch_test() {
-        let td = Builder::new().prefix("filetime").tempdir().unwrap();
+    fn set_file_times_ordered_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
         let path = td.path().join("foo.txt");
-        File::create(&path).unwrap();
-
-        let metadata = fs::metadata(&path).unwrap();
-        let mtime = FileTime::from_last_modification_time(&metadata);
-        let atime = FileTime::from_last_access_time(&metadata);
-        set_file_times(&path, atime, mtime).unwrap();
+        File::create(&path)?;
 
-        let new_mtime = FileTime::from_unix_time(-10_000, 0);
-        if cfg!(target_os = "aix") {
-            // On AIX, os checks if the unix timestamp is valid.
-            let result = set_file_times(&path, atime, new_mtime);
-            assert!(result.is_err());
-            assert!(result.err().unwrap().kind() == std::io::ErrorKind::InvalidInput);
+        let past = FileTime::from_unix_time(10_000, 0);
+        let warning = set_file_times_ordered(&path, past, past)?;
+        if cfg!(unix) {
+            let warning = warning.unwrap();
+            assert_eq!(warning.mtime(), past);
+            assert!(warning.ctime().unix_seconds() > past.unix_seconds());
         } else {
-            set_file_times(&path, atime, new_mtime).unwrap();
+            assert!(warning.is_none());
+        }
 
-            let metadata = fs::metadata(&path).unwrap();
-            let mtime = FileTime::from_last_modification_time(&metadata);
-            assert_eq!(mtime, new_mtime);
+        let future = FileTime::from_unix_time(FileTime::now().unix_seconds() + 3600, 0);
+        assert!(set_file_times_ordered(&path, future, future)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn from_secs_test() {
+        for &x in &[0, 1_500_000_000, -86_400, -1] {
+            let ft = FileTime::from_secs(x);
+            assert_eq!(ft.secs(), x);
+            assert_eq!(ft.nanoseconds(), 0);
+            assert_eq!(ft, FileTime::from_unix_time(x, 0));
         }
     }
 
     #[test]
-    #[cfg(windows)]
-    fn set_file_times_pre_windows_epoch_test() {
-        let td = Builder::new().prefix("filetime").tempdir().unwrap();
+    #[cfg(target_os = "linux")]
+    #[cfg_attr(
+        feature = "force-utimes",
+        ignore = "utimes only sets microsecond precision"
+    )]
+    fn set_file_times_proc_self_fd_test() -> io::Result<()> {
+        use std::os::unix::prelude::*;
+
+        let td = Builder::new().prefix("filetime").tempdir()?;
         let path = td.path().join("foo.txt");
-        File::create(&path).unwrap();
+        let f = File::create(&path)?;
+        let by_path = format!("/proc/self/fd/{}", f.as_raw_fd());
 
-        let metadata = fs::metadata(&path).unwrap();
-        let mtime = FileTime::from_last_modification_time(&metadata);
-        let atime = FileTime::from_last_access_time(&metadata);
-        set_file_times(&path, atime, mtime).unwrap();
+        let first = FileTime::from_unix_time(10_000, 123_456_789);
+        set_file_times(&by_path, first, first)?;
+        assert_eq!(mtime_of(&path)?, first);
 
-        let new_mtime = FileTime::from_unix_time(-12_000_000_000, 0);
-        assert!(set_file_times(&path, atime, new_mtime).is_err());
+        // The descriptor still names the file after it's been unlinked.
+        fs::remove_file(&path)?;
+        let second = FileTime::from_unix_time(20_000, 987_654_321);
+        set_file_times(&by_path, second, second)?;
+        let by_path_mtime = FileTime::from_last_modification_time(&f.metadata()?);
+
+        let third = FileTime::from_unix_time(30_000, 5);
+        set_file_handle_times(&f, None, Some(third))?;
+        let by_handle_mtime = FileTime::from_last_modification_time(&f.metadata()?);
+
+        assert_eq!(by_path_mtime, second);
+        assert_eq!(by_handle_mtime, third);
+        Ok(())
     }
 
     #[test]
-    fn set_symlink_file_times_test() {
-        let td = Builder::new().prefix("filetime").tempdir().unwrap();
+    fn set_times_builder_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
         let path = td.path().join("foo.txt");
-        File::create(&path).unwrap();
+        File::create(&path)?;
+        let atime = FileTime::from_unix_time(10_000, 0);
+        let mtime = FileTime::from_unix_time(20_000, 0);
 
-        let metadata = fs::metadata(&path).unwrap();
-        let mtime = FileTime::from_last_modification_time(&metadata);
-        let atime = FileTime::from_last_access_time(&metadata);
-        set_symlink_file_times(&path, atime, mtime).unwrap();
+        SetTimes::new()
+            .atime(atime)
+            .mtime(mtime)
+            .apply(&path, true)?;
+        assert_eq!(atime_of(&path)?, atime);
+        assert_eq!(mtime_of(&path)?, mtime);
 
-        let new_mtime = FileTime::from_unix_time(10_000, 0);
-        set_symlink_file_times(&path, atime, new_mtime).unwrap();
+        // Times which aren't selected are left alone.
+        SetTimes::new().apply(&path, true)?;
+        let new_mtime = FileTime::from_unix_time(30_000, 0);
+        SetTimes::new().mtime(new_mtime).apply(&path, true)?;
+        assert_eq!(atime_of(&path)?, atime);
+        assert_eq!(mtime_of(&path)?, new_mtime);
 
-        let metadata = fs::metadata(&path).unwrap();
-        let mtime = FileTime::from_last_modification_time(&metadata);
-        assert_eq!(mtime, new_mtime);
+        let before = FileTime::now();
+        SetTimes::new()
+            .mtime(mtime)
+            .mtime_now()
+            .apply(&path, true)?;
+        assert!(mtime_of(&path)?.unix_seconds() >= before.unix_seconds());
+        assert_eq!(atime_of(&path)?, atime);
 
         let spath = td.path().join("bar.txt");
-        make_symlink_file(&path, &spath).unwrap();
+        make_symlink_file(&path, &spath)?;
+        let link_atime = FileTime::from_unix_time(40_000, 0);
+        SetTimes::new().atime(link_atime).apply(&spath, false)?;
+        let metadata = fs::symlink_metadata(&spath)?;
+        assert_eq!(FileTime::from_last_access_time(&metadata), link_atime);
+        assert_eq!(atime_of(&path)?, atime);
 
-        let metadata = fs::symlink_metadata(&spath).unwrap();
-        let smtime = FileTime::from_last_modification_time(&metadata);
-        let satime = FileTime::from_last_access_time(&metadata);
-        set_symlink_file_times(&spath, smtime, satime).unwrap();
+        let result = SetTimes::new().crtime(mtime).apply(&path, true);
+        if cfg!(windows) {
+            result?;
+            let metadata = fs::metadata(&path)?;
+            assert_eq!(FileTime::from_creation_time(&metadata), Some(mtime));
+        } else {
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::Unsupported);
+        }
+        Ok(())
+    }
 
-        let metadata = fs::metadata(&path).unwrap();
-        let mtime = FileTime::from_last_modification_time(&metadata);
-        assert_eq!(mtime, new_mtime);
+    #[test]
+    #[cfg(all(unix, not(target_os = "redox")))]
+    fn eoverflow_is_described_test() {
+        let e = crate::imp::map_time_error(io::Error::from_raw_os_error(libc::EOVERFLOW));
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(e.to_string(), "timestamp exceeds platform time_t range");
 
-        let new_smtime = FileTime::from_unix_time(20_000, 0);
-        set_symlink_file_times(&spath, atime, new_smtime).unwrap();
+        let e = crate::imp::map_time_error(io::Error::from_raw_os_error(libc::ENOENT));
+        assert_eq!(e.raw_os_error(), Some(libc::ENOENT));
+    }
 
-        let metadata = fs::metadata(&spath).unwrap();
-        let mtime = FileTime::from_last_modification_time(&metadata);
-        assert_eq!(mtime, new_mtime);
+    #[test]
+    fn nanos_diff_test() {
+        let a = FileTime::from_unix_time(10, 200_000_000);
+        let b = FileTime::from_unix_time(9, 900_000_000);
+        assert_eq!(a.nanos_diff(&b), 300_000_000);
+        assert_eq!(b.nanos_diff(&a), -300_000_000);
+        assert_eq!(a.nanos_diff(&a), 0);
 
-        let metadata = fs::symlink_metadata(&spath).unwrap();
-        let mtime = FileTime::from_last_modification_time(&metadata);
-        assert_eq!(mtime, new_smtime);
+        let pre_epoch = FileTime::from_unix_time(-2, 500_000_000);
+        assert_eq!(a.nanos_diff(&pre_epoch), 11_700_000_000);
+        assert_eq!(
+            pre_epoch.nanos_diff(&FileTime::from_secs(0)),
+            -1_500_000_000
+        );
+
+        let min = FileTime {
+            seconds: i64::MIN,
+            nanos: 0,
+        };
+        let max = FileTime {
+            seconds: i64::MAX,
+            nanos: 999_999_999,
+        };
+        assert!(max.nanos_diff(&min) > 0);
+        assert!(min.nanos_diff(&max) < 0);
     }
 
     #[test]
-    fn set_symlink_dir_times_test() {
-        let td = Builder::new().prefix("filetime").tempdir().unwrap();
-        let path = td.path().join("foo");
-        fs::create_dir(&path).unwrap();
+    #[cfg(windows)]
+    fn set_file_times_posix_semantics_test() -> io::Result<()> {
+        use super::set_file_times_posix_semantics;
 
-        let metadata = fs::metadata(&path).unwrap();
-        let mtime = FileTime::from_last_modification_time(&metadata);
-        let atime = FileTime::from_last_access_time(&metadata);
-        set_symlink_file_times(&path, atime, mtime).unwrap();
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
 
-        let new_mtime = FileTime::from_unix_time(10_000, 0);
-        set_symlink_file_times(&path, atime, new_mtime).unwrap();
+        let atime = FileTime::from_unix_time(10_000, 100);
+        let mtime = FileTime::from_unix_time(20_000, 200);
+        set_file_times_posix_semantics(&path, atime, mtime)?;
+        assert_eq!(atime_of(&path)?, atime);
+        assert_eq!(mtime_of(&path)?, mtime);
+        Ok(())
+    }
 
-        let metadata = fs::metadata(&path).unwrap();
-        let mtime = FileTime::from_last_modification_time(&metadata);
-        assert_eq!(mtime, new_mtime);
+    #[test]
+    fn touch_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        let atime = FileTime::from_unix_time(10_000, 0);
+        let mtime = FileTime::from_unix_time(20_000, 0);
 
-        let spath = td.path().join("bar");
-        make_symlink_dir(&path, &spath).unwrap();
+        let err = touch_existing(&path, atime, mtime).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert!(!path.exists());
 
-        let metadata = fs::symlink_metadata(&spath).unwrap();
-        let smtime = FileTime::from_last_modification_time(&metadata);
-        let satime = FileTime::from_last_access_time(&metadata);
-        set_symlink_file_times(&spath, smtime, satime).unwrap();
+        touch_or_create(&path, atime, mtime)?;
+        assert_eq!(fs::metadata(&path)?.len(), 0);
+        assert_eq!(mtime_of(&path)?, mtime);
 
-        let metadata = fs::metadata(&path).unwrap();
-        let mtime = FileTime::from_last_modification_time(&metadata);
-        assert_eq!(mtime, new_mtime);
+        fs::write(&path, b"contents")?;
+        let mtime = FileTime::from_unix_time(30_000, 0);
+        touch_or_create(&path, atime, mtime)?;
+        assert_eq!(fs::read(&path)?, b"contents");
+        assert_eq!(mtime_of(&path)?, mtime);
 
-        let new_smtime = FileTime::from_unix_time(20_000, 0);
-        set_symlink_file_times(&spath, atime, new_smtime).unwrap();
+        let mtime = FileTime::from_unix_time(40_000, 0);
+        touch_existing(&path, atime, mtime)?;
+        assert_eq!(atime_of(&path)?, atime);
+        assert_eq!(mtime_of(&path)?, mtime);
+        Ok(())
+    }
 
-        let metadata = fs::metadata(&spath).unwrap();
-        let mtime = FileTime::from_last_modification_time(&metadata);
-        assert_eq!(mtime, new_mtime);
+    #[test]
+    #[cfg(all(unix, not(target_os = "redox")))]
+    fn set_file_times_device_node_test() -> io::Result<()> {
+        use std::ffi::CString;
+        use std::os::unix::prelude::*;
 
-        let metadata = fs::symlink_metadata(&spath).unwrap();
-        let mtime = FileTime::from_last_modification_time(&metadata);
-        assert_eq!(mtime, new_smtime);
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("null");
+        let cpath = CString::new(path.as_os_str().as_bytes())?;
+        // The same device numbers as `/dev/null` on Linux. `makedev` is only
+        // an unsafe extern function on some platforms, such as illumos.
+        #[allow(unused_unsafe)]
+        let dev = unsafe { libc::makedev(1, 3) };
+        if unsafe { libc::mknod(cpath.as_ptr(), libc::S_IFCHR | 0o666, dev) } != 0 {
+            // Creating device nodes needs privileges which tests often lack.
+            return Ok(());
+        }
+
+        let atime = FileTime::from_unix_time(10_000, 0);
+        let mtime = FileTime::from_unix_time(20_000, 0);
+        set_file_times(&path, atime, mtime)?;
+        let metadata = fs::symlink_metadata(&path)?;
+        assert!(metadata.file_type().is_char_device());
+        assert_eq!(FileTime::from_last_access_time(&metadata), atime);
+        assert_eq!(FileTime::from_last_modification_time(&metadata), mtime);
+
+        let mtime = FileTime::from_unix_time(30_000, 0);
+        set_file_mtime(&path, mtime)?;
+        assert_eq!(mtime_of(&path)?, mtime);
+        assert_eq!(atime_of(&path)?, atime);
+        Ok(())
     }
 
     #[test]
-    fn set_single_time_test() {
-        use super::{set_file_atime, set_file_mtime};
+    fn le_bytes_invalid_test() {
+        // One billion nanoseconds.
+        let bytes = [0, 0, 0, 0, 0, 0, 0, 0, 0x00, 0xca, 0x9a, 0x3b];
+        assert_eq!(FileTime::from_le_bytes(bytes), None);
+        let bytes = [0xff; 12];
+        assert_eq!(FileTime::from_le_bytes(bytes), None);
 
-        let td = Builder::new().prefix("filetime").tempdir().unwrap();
+        let mut bytes = [0; 12];
+        bytes[..8].copy_from_slice(&i64::MAX.to_le_bytes());
+        assert_eq!(FileTime::from_le_bytes(bytes).is_none(), cfg!(windows));
+    }
+
+    #[test]
+    fn set_times_builder_now_test() -> io::Result<()> {
+        let td = Builder::new().prefix("filetime").tempdir()?;
         let path = td.path().join("foo.txt");
-        File::create(&path).unwrap();
+        File::create(&path)?;
+        let past = FileTime::from_unix_time(10_000, 0);
+        set_file_times(&path, past, past)?;
 
-        let metadata = fs::metadata(&path).unwrap();
-        let mtime = FileTime::from_last_modification_time(&metadata);
-        let atime = FileTime::from_last_access_time(&metadata);
-        set_file_times(&path, atime, mtime).unwrap();
+        let before = FileTime::now();
+        SetTimes::new().atime_now().mtime_now().apply(&path, true)?;
+        assert!(atime_of(&path)?.unix_seconds() >= before.unix_seconds());
+        assert!(mtime_of(&path)?.unix_seconds() >= before.unix_seconds());
 
-        let new_mtime = FileTime::from_unix_time(10_000, 0);
-        set_file_mtime(&path, new_mtime).unwrap();
+        set_file_times(&path, past, past)?;
+        SetTimes::new().atime_now().apply(&path, true)?;
+        assert!(atime_of(&path)?.unix_seconds() >= before.unix_seconds());
+        assert_eq!(mtime_of(&path)?, past);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn time_arg_now_test() -> io::Result<()> {
+        use crate::imp::TimeArg;
+        use crate::{UTIME_NOW, UTIME_OMIT};
 
-        let metadata = fs::metadata(&path).unwrap();
-        let mtime = FileTime::from_last_modification_time(&metadata);
-        assert_eq!(mtime, new_mtime, "modification time should be updated");
         assert_eq!(
-            atime,
-            FileTime::from_last_access_time(&metadata),
-            "access time should not be updated",
+            TimeArg::Now.to_timespec()?.tv_nsec,
+            UTIME_NOW as libc::c_long
         );
-
-        let new_atime = FileTime::from_unix_time(20_000, 0);
-        set_file_atime(&path, new_atime).unwrap();
-
-        let metadata = fs::metadata(&path).unwrap();
-        let atime = FileTime::from_last_access_time(&metadata);
-        assert_eq!(atime, new_atime, "access time should be updated");
         assert_eq!(
-            mtime,
-            FileTime::from_last_modification_time(&metadata),
-            "modification time should not be updated"
+            TimeArg::Omit.to_timespec()?.tv_nsec,
+            UTIME_OMIT as libc::c_long
         );
+        let ts = TimeArg::At(FileTime::from_unix_time(10_000, 5)).to_timespec()?;
+        assert_eq!((ts.tv_sec, ts.tv_nsec), (10_000, 5));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(unix, not(target_os = "redox")))]
+    fn set_file_times_single_call_test() -> io::Result<()> {
+        use super::imp;
+
+        let td = Builder::new().prefix("filetime").tempdir()?;
+        let path = td.path().join("foo.txt");
+        File::create(&path)?;
+        let atime = FileTime::from_unix_time(10_000, 0);
+        let mtime = FileTime::from_unix_time(20_000, 0);
+
+        imp::TIME_CALLS.with(|calls| calls.set(0));
+        set_file_times(&path, atime, mtime)?;
+        assert_eq!(imp::TIME_CALLS.with(|calls| calls.get()), 1);
+        assert_eq!(atime_of(&path)?, atime);
+        assert_eq!(mtime_of(&path)?, mtime);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "arbitrary")]
+    fn arbitrary_test() -> arbitrary::Result<()> {
+        use arbitrary::{Arbitrary, Unstructured};
+
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let bytes: Vec<u8> = (0..64 * 1024)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        let mut u = Unstructured::new(&bytes);
+        let (mut zero, mut pre_epoch) = (false, false);
+        while !u.is_empty() {
+            let ft = FileTime::arbitrary(&mut u)?;
+            assert!(ft.nanoseconds() < 1_000_000_000, "{:?}", ft);
+            assert_eq!(
+                FileTime::from_unix_time(ft.unix_seconds(), ft.nanoseconds()),
+                ft
+            );
+            zero |= ft.is_zero();
+            pre_epoch |= ft.unix_seconds() < 0;
+        }
+        assert!(zero && pre_epoch);
+
+        // Running out of data still produces a valid time.
+        let ft = FileTime::arbitrary(&mut Unstructured::new(&[]))?;
+        assert_eq!(ft, FileTime::zero());
+        Ok(())
+    }
+
+    #[cfg(feature = "proptest")]
+    proptest::proptest! {
+        #[test]
+        fn filetime_strategy_test(ft in super::filetime_strategy()) {
+            proptest::prop_assert!(ft.nanoseconds() < 1_000_000_000);
+            proptest::prop_assert_eq!(FileTime::from_le_bytes(ft.to_le_bytes()), Some(ft));
+        }
     }
 }
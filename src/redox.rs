@@ -1,4 +1,5 @@
 use crate::FileTime;
+use std::ffi::{CStr, OsStr};
 use std::fs::{self, File};
 use std::io;
 use std::os::unix::prelude::*;
@@ -15,34 +16,32 @@ pub fn set_file_times(p: &Path, atime: FileTime, mtime: FileTime) -> io::Result<
     set_file_times_redox(fd.raw(), atime, mtime)
 }
 
-pub fn set_file_mtime(p: &Path, mtime: FileTime) -> io::Result<()> {
-    let fd = open_redox(p, 0)?;
-    let st = fd.stat()?;
+pub fn set_file_times_cstr(p: &CStr, atime: FileTime, mtime: FileTime) -> io::Result<()> {
+    set_file_times(Path::new(OsStr::from_bytes(p.to_bytes())), atime, mtime)
+}
 
-    set_file_times_redox(
-        fd.raw(),
-        FileTime {
-            seconds: st.st_atime as i64,
-            nanos: st.st_atime_nsec as u32,
-        },
-        mtime,
-    )?;
-    Ok(())
+pub fn set_file_mtime(p: &Path, mtime: FileTime) -> io::Result<()> {
+    set_single_time(p, None, Some(mtime))
 }
 
 pub fn set_file_atime(p: &Path, atime: FileTime) -> io::Result<()> {
+    set_single_time(p, Some(atime), None)
+}
+
+// Reads the time which isn't being set through the same descriptor that the
+// times are then written with, so that only one file is ever involved.
+fn set_single_time(p: &Path, atime: Option<FileTime>, mtime: Option<FileTime>) -> io::Result<()> {
     let fd = open_redox(p, 0)?;
     let st = fd.stat()?;
-
-    set_file_times_redox(
-        fd.raw(),
-        atime,
-        FileTime {
-            seconds: st.st_mtime as i64,
-            nanos: st.st_mtime_nsec as u32,
-        },
-    )?;
-    Ok(())
+    let atime = atime.unwrap_or(FileTime {
+        seconds: st.st_atime as i64,
+        nanos: crate::clamp_nsec(st.st_atime_nsec as i64),
+    });
+    let mtime = mtime.unwrap_or(FileTime {
+        seconds: st.st_mtime as i64,
+        nanos: crate::clamp_nsec(st.st_mtime_nsec as i64),
+    });
+    set_file_times_redox(fd.raw(), atime, mtime)
 }
 
 pub fn set_symlink_file_times(p: &Path, atime: FileTime, mtime: FileTime) -> io::Result<()> {
@@ -81,33 +80,69 @@ fn open_redox(path: &Path, flags: i32) -> Result<Fd> {
 fn set_file_times_redox(fd: usize, atime: FileTime, mtime: FileTime) -> io::Result<()> {
     use libredox::data::TimeSpec;
 
-    fn to_timespec(ft: &FileTime) -> TimeSpec {
-        TimeSpec {
-            tv_sec: ft.seconds(),
-            tv_nsec: ft.nanoseconds() as i64,
+    // `FileTime` only holds valid nanoseconds when built through its
+    // constructors, so check rather than truncate in case a malformed value
+    // slips through.
+    fn to_timespec(ft: &FileTime) -> io::Result<TimeSpec> {
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "nanoseconds must be less than one billion",
+            )
+        };
+        if ft.nanoseconds() >= 1_000_000_000 {
+            return Err(invalid());
         }
+        Ok(TimeSpec {
+            tv_sec: ft.seconds(),
+            tv_nsec: ft.nanoseconds().into(),
+        })
     }
 
-    let times = [to_timespec(&atime), to_timespec(&mtime)];
+    let times = [to_timespec(&atime)?, to_timespec(&mtime)?];
 
     call::futimens(fd, &times)?;
     Ok(())
 }
 
+pub fn supports_nanosecond_times() -> bool {
+    true
+}
+
+pub fn is_unsupported_error(e: &io::Error) -> bool {
+    match e.raw_os_error() {
+        Some(code) => code == libc::EROFS || code == libc::EPERM || code == libc::EOPNOTSUPP,
+        None => e.kind() == io::ErrorKind::Unsupported,
+    }
+}
+
+pub fn check_range(_ft: &FileTime) -> io::Result<()> {
+    Ok(())
+}
+
+pub const SUPPORTS_CREATION_TIME: bool = false;
+
 pub fn from_last_modification_time(meta: &fs::Metadata) -> FileTime {
     FileTime {
         seconds: meta.mtime(),
-        nanos: meta.mtime_nsec() as u32,
+        nanos: crate::clamp_nsec(meta.mtime_nsec()),
     }
 }
 
 pub fn from_last_access_time(meta: &fs::Metadata) -> FileTime {
     FileTime {
         seconds: meta.atime(),
-        nanos: meta.atime_nsec() as u32,
+        nanos: crate::clamp_nsec(meta.atime_nsec()),
     }
 }
 
+pub fn from_status_change_time(meta: &fs::Metadata) -> Option<FileTime> {
+    Some(FileTime {
+        seconds: meta.ctime(),
+        nanos: crate::clamp_nsec(meta.ctime_nsec()),
+    })
+}
+
 pub fn from_creation_time(_meta: &fs::Metadata) -> Option<FileTime> {
     None
 }
@@ -1,5 +1,6 @@
+use super::TimeArg;
 use crate::FileTime;
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
 use std::fs::File;
 use std::io;
 use std::os::unix::prelude::*;
@@ -17,22 +18,41 @@ pub fn set_file_atime(p: &Path, atime: FileTime) -> io::Result<()> {
     set_times(p, Some(atime), None, false)
 }
 
+pub fn set_file_times_cstr(p: &CStr, atime: FileTime, mtime: FileTime) -> io::Result<()> {
+    set_times_cstr(p, Some(atime), Some(mtime), false)
+}
+
+pub fn set_file_times_at(
+    dirfd: RawFd,
+    p: &CStr,
+    atime: FileTime,
+    mtime: FileTime,
+    symlink: bool,
+) -> io::Result<()> {
+    set_times_at(dirfd, p, Some(atime), Some(mtime), symlink)
+}
+
+pub fn supports_nanosecond_times() -> bool {
+    true
+}
+
 pub fn set_file_handle_times(
     f: &File,
     atime: Option<FileTime>,
     mtime: Option<FileTime>,
 ) -> io::Result<()> {
-    let times = [super::to_timespec(&atime), super::to_timespec(&mtime)];
+    let times = [super::to_timespec(&atime)?, super::to_timespec(&mtime)?];
 
     // On Android NDK before version 19, `futimens` is not available.
     //
     // For better compatibility, we reimplement `futimens` using `utimensat`,
     // the same way as bionic libc uses it to implement `futimens`.
+    super::count_time_call();
     let rc = unsafe { libc::utimensat(f.as_raw_fd(), core::ptr::null(), times.as_ptr(), 0) };
     if rc == 0 {
         Ok(())
     } else {
-        Err(io::Error::last_os_error())
+        Err(super::last_time_error())
     }
 }
 
@@ -40,24 +60,54 @@ pub fn set_symlink_file_times(p: &Path, atime: FileTime, mtime: FileTime) -> io:
     set_times(p, Some(atime), Some(mtime), true)
 }
 
+pub fn set_time_args(p: &Path, atime: TimeArg, mtime: TimeArg, symlink: bool) -> io::Result<()> {
+    let p = CString::new(p.as_os_str().as_bytes())?;
+    let times = [atime.to_timespec()?, mtime.to_timespec()?];
+    utimensat(libc::AT_FDCWD, &p, &times, symlink)
+}
+
 fn set_times(
     p: &Path,
     atime: Option<FileTime>,
     mtime: Option<FileTime>,
     symlink: bool,
 ) -> io::Result<()> {
+    let p = CString::new(p.as_os_str().as_bytes())?;
+    set_times_cstr(&p, atime, mtime, symlink)
+}
+
+fn set_times_cstr(
+    p: &CStr,
+    atime: Option<FileTime>,
+    mtime: Option<FileTime>,
+    symlink: bool,
+) -> io::Result<()> {
+    set_times_at(libc::AT_FDCWD, p, atime, mtime, symlink)
+}
+
+fn set_times_at(
+    dirfd: RawFd,
+    p: &CStr,
+    atime: Option<FileTime>,
+    mtime: Option<FileTime>,
+    symlink: bool,
+) -> io::Result<()> {
+    let times = [super::to_timespec(&atime)?, super::to_timespec(&mtime)?];
+    utimensat(dirfd, p, &times, symlink)
+}
+
+fn utimensat(dirfd: RawFd, p: &CStr, times: &[libc::timespec; 2], symlink: bool) -> io::Result<()> {
     let flags = if symlink {
         libc::AT_SYMLINK_NOFOLLOW
     } else {
         0
     };
 
-    let p = CString::new(p.as_os_str().as_bytes())?;
-    let times = [super::to_timespec(&atime), super::to_timespec(&mtime)];
-    let rc = unsafe { libc::utimensat(libc::AT_FDCWD, p.as_ptr(), times.as_ptr(), flags) };
+    super::count_time_call();
+    let rc = unsafe { libc::utimensat(dirfd, p.as_ptr(), times.as_ptr(), flags) };
     if rc == 0 {
         Ok(())
     } else {
-        Err(io::Error::last_os_error())
+        Err(super::last_time_error())
     }
 }
@@ -1,9 +1,14 @@
 //! On Linux we try to use the more accurate `utimensat` syscall but this isn't
 //! always available so we also fall back to `utimes` if we couldn't find
 //! `utimensat` at runtime.
+//!
+//! With the `force-utimes` feature enabled the detection is skipped and the
+//! `utimes` family is always used, so times are only ever set with
+//! microsecond precision regardless of what the kernel supports.
 
+use super::TimeArg;
 use crate::FileTime;
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
 use std::fs;
 use std::io;
 use std::os::unix::prelude::*;
@@ -12,6 +17,12 @@ use std::ptr;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering::SeqCst;
 
+// Whether `utimensat` has returned `ENOSYS` when passed a file descriptor or a
+// path respectively. These are tracked separately so a failure of one form
+// doesn't force the other onto the less precise fallback.
+static HANDLE_UTIMENSAT_UNSUPPORTED: AtomicBool = AtomicBool::new(false);
+static PATH_UTIMENSAT_UNSUPPORTED: AtomicBool = AtomicBool::new(false);
+
 pub fn set_file_times(p: &Path, atime: FileTime, mtime: FileTime) -> io::Result<()> {
     set_times(p, Some(atime), Some(mtime), false)
 }
@@ -24,6 +35,46 @@ pub fn set_file_atime(p: &Path, atime: FileTime) -> io::Result<()> {
     set_times(p, Some(atime), None, false)
 }
 
+pub fn set_file_times_cstr(p: &CStr, atime: FileTime, mtime: FileTime) -> io::Result<()> {
+    set_times_cstr(p, Some(atime), Some(mtime), false)
+}
+
+pub fn set_file_times_at(
+    dirfd: RawFd,
+    p: &CStr,
+    atime: FileTime,
+    mtime: FileTime,
+    symlink: bool,
+) -> io::Result<()> {
+    if cfg!(feature = "force-utimes") {
+        return super::utimes::set_file_times_at(dirfd, p, atime, mtime, symlink);
+    }
+    set_times_at(dirfd, p, Some(atime), Some(mtime), nofollow(symlink))
+}
+
+pub fn set_file_times_opath(p: &Path, atime: FileTime, mtime: FileTime) -> io::Result<()> {
+    if cfg!(feature = "force-utimes") {
+        return set_file_times(p, atime, mtime);
+    }
+    let p = CString::new(p.as_os_str().as_bytes())?;
+    let fd = unsafe { libc::open(p.as_ptr(), libc::O_PATH | libc::O_CLOEXEC) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    // `O_PATH` descriptors can't be passed to `futimens`, but an empty path
+    // relative to one refers to the file itself.
+    let empty = CStr::from_bytes_with_nul(b"\0").unwrap();
+    set_times_at(
+        fd.as_raw_fd(),
+        empty,
+        Some(atime),
+        Some(mtime),
+        libc::AT_EMPTY_PATH,
+    )
+}
+
 pub fn set_file_handle_times(
     f: &fs::File,
     atime: Option<FileTime>,
@@ -31,87 +82,176 @@ pub fn set_file_handle_times(
 ) -> io::Result<()> {
     // Attempt to use the `utimensat` syscall, but if it's not supported by the
     // current kernel then fall back to an older syscall.
-    static INVALID: AtomicBool = AtomicBool::new(false);
-    if !INVALID.load(SeqCst) {
-        let times = [super::to_timespec(&atime), super::to_timespec(&mtime)];
-
-        // We normally use a syscall because the `utimensat` function is documented
-        // as not accepting a file descriptor in the first argument (even though, on
-        // Linux, the syscall itself can accept a file descriptor there).
-        #[cfg(not(target_env = "musl"))]
-        let rc = unsafe {
-            libc::syscall(
-                libc::SYS_utimensat,
-                f.as_raw_fd(),
-                ptr::null::<libc::c_char>(),
-                times.as_ptr(),
-                0,
-            )
-        };
-        // However, on musl, we call the musl libc function instead. This is because
-        // on newer musl versions starting with musl 1.2, `timespec` is always a 64-bit
-        // value even on 32-bit targets. As a result, musl internally converts their
-        // `timespec` values to the correct ABI before invoking the syscall. Since we
-        // use `timespec` from the libc crate, it matches musl's definition and not
-        // the Linux kernel's version (for some platforms) so we must use musl's
-        // `utimensat` function to properly convert the value. musl's `utimensat`
-        // function allows file descriptors in the path argument so this is fine.
-        #[cfg(target_env = "musl")]
-        let rc = unsafe {
-            libc::utimensat(
-                f.as_raw_fd(),
-                ptr::null::<libc::c_char>(),
-                times.as_ptr(),
-                0,
-            )
-        };
-
-        if rc == 0 {
-            return Ok(());
-        }
-        let err = io::Error::last_os_error();
-        if err.raw_os_error() == Some(libc::ENOSYS) {
-            INVALID.store(true, SeqCst);
-        } else {
-            return Err(err);
-        }
-    }
+    with_fallback(
+        &HANDLE_UTIMENSAT_UNSUPPORTED,
+        || {
+            let times = [super::to_timespec(&atime)?, super::to_timespec(&mtime)?];
+            super::count_time_call();
+
+            // We normally use a syscall because the `utimensat` function is documented
+            // as not accepting a file descriptor in the first argument (even though, on
+            // Linux, the syscall itself can accept a file descriptor there).
+            #[cfg(not(any(target_env = "musl", target_env = "ohos")))]
+            let rc = unsafe {
+                libc::syscall(
+                    libc::SYS_utimensat,
+                    f.as_raw_fd(),
+                    ptr::null::<libc::c_char>(),
+                    times.as_ptr(),
+                    0,
+                )
+            };
+            // However, on musl, we call the musl libc function instead. This is because
+            // on newer musl versions starting with musl 1.2, `timespec` is always a 64-bit
+            // value even on 32-bit targets. As a result, musl internally converts their
+            // `timespec` values to the correct ABI before invoking the syscall. Since we
+            // use `timespec` from the libc crate, it matches musl's definition and not
+            // the Linux kernel's version (for some platforms) so we must use musl's
+            // `utimensat` function to properly convert the value. musl's `utimensat`
+            // function allows file descriptors in the path argument so this is fine.
+            // OpenHarmony's libc is derived from musl and behaves the same way.
+            #[cfg(any(target_env = "musl", target_env = "ohos"))]
+            let rc = unsafe {
+                libc::utimensat(
+                    f.as_raw_fd(),
+                    ptr::null::<libc::c_char>(),
+                    times.as_ptr(),
+                    0,
+                )
+            };
 
-    super::utimes::set_file_handle_times(f, atime, mtime)
+            if rc == 0 {
+                Ok(())
+            } else {
+                Err(super::last_time_error())
+            }
+        },
+        || super::utimes::set_file_handle_times(f, atime, mtime),
+    )
 }
 
 pub fn set_symlink_file_times(p: &Path, atime: FileTime, mtime: FileTime) -> io::Result<()> {
     set_times(p, Some(atime), Some(mtime), true)
 }
 
+pub fn set_time_args(p: &Path, atime: TimeArg, mtime: TimeArg, symlink: bool) -> io::Result<()> {
+    let p = CString::new(p.as_os_str().as_bytes())?;
+    with_fallback(
+        &PATH_UTIMENSAT_UNSUPPORTED,
+        || {
+            let times = [atime.to_timespec()?, mtime.to_timespec()?];
+            utimensat(libc::AT_FDCWD, &p, &times, nofollow(symlink))
+        },
+        || super::utimes::set_time_args_cstr(&p, atime, mtime, symlink),
+    )
+}
+
 fn set_times(
     p: &Path,
     atime: Option<FileTime>,
     mtime: Option<FileTime>,
     symlink: bool,
 ) -> io::Result<()> {
-    let flags = if symlink {
+    let p = CString::new(p.as_os_str().as_bytes())?;
+    set_times_cstr(&p, atime, mtime, symlink)
+}
+
+fn set_times_cstr(
+    p: &CStr,
+    atime: Option<FileTime>,
+    mtime: Option<FileTime>,
+    symlink: bool,
+) -> io::Result<()> {
+    // Set the times through the descriptor named by a `/proc/self/fd` path
+    // directly, falling back to resolving the path if that fails, such as
+    // when the number isn't an open descriptor.
+    if !symlink && !cfg!(feature = "force-utimes") {
+        if let Some(fd) = magic_fd(p) {
+            let empty = CStr::from_bytes_with_nul(b"\0").unwrap();
+            if set_times_at(fd, empty, atime, mtime, libc::AT_EMPTY_PATH).is_ok() {
+                return Ok(());
+            }
+        }
+    }
+
+    // Same as the fallback in `set_file_handle_times` above.
+    with_fallback(
+        &PATH_UTIMENSAT_UNSUPPORTED,
+        || set_times_at(libc::AT_FDCWD, p, atime, mtime, nofollow(symlink)),
+        || super::utimes::set_times_cstr(p, atime, mtime, symlink),
+    )
+}
+
+// There's no fallback for paths relative to a directory other than the
+// current one, as `utimes` can't express them.
+fn set_times_at(
+    dirfd: RawFd,
+    p: &CStr,
+    atime: Option<FileTime>,
+    mtime: Option<FileTime>,
+    flags: libc::c_int,
+) -> io::Result<()> {
+    let times = [super::to_timespec(&atime)?, super::to_timespec(&mtime)?];
+    utimensat(dirfd, p, &times, flags)
+}
+
+fn utimensat(
+    dirfd: RawFd,
+    p: &CStr,
+    times: &[libc::timespec; 2],
+    flags: libc::c_int,
+) -> io::Result<()> {
+    super::count_time_call();
+    let rc = unsafe { libc::utimensat(dirfd, p.as_ptr(), times.as_ptr(), flags) };
+    if rc == 0 {
+        Ok(())
+    } else {
+        Err(super::last_time_error())
+    }
+}
+
+// Returns the descriptor named by a `/proc/self/fd/N` or `/dev/fd/N` path.
+fn magic_fd(p: &CStr) -> Option<RawFd> {
+    let p = p.to_bytes();
+    let n = p
+        .strip_prefix(b"/proc/self/fd/")
+        .or_else(|| p.strip_prefix(b"/dev/fd/"))?;
+    if n.is_empty() || !n.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    std::str::from_utf8(n).ok()?.parse().ok()
+}
+
+fn nofollow(symlink: bool) -> libc::c_int {
+    if symlink {
         libc::AT_SYMLINK_NOFOLLOW
     } else {
         0
-    };
-
-    // Same as the `if` statement above.
-    static INVALID: AtomicBool = AtomicBool::new(false);
-    if !INVALID.load(SeqCst) {
-        let p = CString::new(p.as_os_str().as_bytes())?;
-        let times = [super::to_timespec(&atime), super::to_timespec(&mtime)];
-        let rc = unsafe { libc::utimensat(libc::AT_FDCWD, p.as_ptr(), times.as_ptr(), flags) };
-        if rc == 0 {
-            return Ok(());
-        }
-        let err = io::Error::last_os_error();
-        if err.raw_os_error() == Some(libc::ENOSYS) {
-            INVALID.store(true, SeqCst);
-        } else {
-            return Err(err);
-        }
     }
+}
+
+// The shared `with_fallback`, except that the `force-utimes` feature always
+// uses `fallback`.
+fn with_fallback(
+    unsupported: &AtomicBool,
+    attempt: impl FnOnce() -> io::Result<()>,
+    fallback: impl FnOnce() -> io::Result<()>,
+) -> io::Result<()> {
+    if cfg!(feature = "force-utimes") {
+        return fallback();
+    }
+    super::with_fallback(unsupported, attempt, fallback)
+}
+
+/// Returns whether `utimensat` has been found to be unsupported for file
+/// descriptors and paths respectively.
+pub fn utimensat_unsupported() -> (bool, bool) {
+    (
+        HANDLE_UTIMENSAT_UNSUPPORTED.load(SeqCst),
+        PATH_UTIMENSAT_UNSUPPORTED.load(SeqCst),
+    )
+}
 
-    super::utimes::set_times(p, atime, mtime, symlink)
+pub fn supports_nanosecond_times() -> bool {
+    !cfg!(feature = "force-utimes") && utimensat_unsupported() == (false, false)
 }
@@ -1,13 +1,13 @@
 //! Beginning with macOS 10.13, `utimensat` is supported by the OS, so here, we check if the symbol exists
 //! and if not, we fallback to `utimes`.
+use super::TimeArg;
 use crate::FileTime;
-use libc::{c_char, c_int, timespec};
+use libc::{c_char, c_int, c_void, timespec};
 use std::ffi::{CStr, CString};
 use std::fs::File;
 use std::os::unix::prelude::*;
 use std::path::Path;
-use std::sync::atomic::AtomicUsize;
-use std::sync::atomic::Ordering::SeqCst;
+use std::sync::OnceLock;
 use std::{io, mem};
 
 pub fn set_file_times(p: &Path, atime: FileTime, mtime: FileTime) -> io::Result<()> {
@@ -22,6 +22,43 @@ pub fn set_file_atime(p: &Path, atime: FileTime) -> io::Result<()> {
     set_times(p, Some(atime), None, false)
 }
 
+pub fn set_file_times_cstr(p: &CStr, atime: FileTime, mtime: FileTime) -> io::Result<()> {
+    set_times_cstr(p, Some(atime), Some(mtime), false)
+}
+
+pub fn set_file_times_at(
+    dirfd: RawFd,
+    p: &CStr,
+    atime: FileTime,
+    mtime: FileTime,
+    symlink: bool,
+) -> io::Result<()> {
+    // Unlike for the current directory there's no fallback, as `utimes` can't
+    // express paths relative to another directory.
+    let func = utimensat().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "utimensat is not available on this version of macOS",
+        )
+    })?;
+    let flags = if symlink {
+        libc::AT_SYMLINK_NOFOLLOW
+    } else {
+        0
+    };
+    let times = [
+        super::to_timespec(&Some(atime))?,
+        super::to_timespec(&Some(mtime))?,
+    ];
+    super::count_time_call();
+    let rc = unsafe { func(dirfd, p.as_ptr(), times.as_ptr(), flags) };
+    if rc == 0 {
+        Ok(())
+    } else {
+        Err(super::last_time_error())
+    }
+}
+
 pub fn set_file_handle_times(
     f: &File,
     atime: Option<FileTime>,
@@ -30,12 +67,13 @@ pub fn set_file_handle_times(
     // Attempt to use the `futimens` syscall, but if it's not supported by the
     // current kernel then fall back to an older syscall.
     if let Some(func) = futimens() {
-        let times = [super::to_timespec(&atime), super::to_timespec(&mtime)];
+        let times = [super::to_timespec(&atime)?, super::to_timespec(&mtime)?];
+        super::count_time_call();
         let rc = unsafe { func(f.as_raw_fd(), times.as_ptr()) };
         if rc == 0 {
             return Ok(());
         } else {
-            return Err(io::Error::last_os_error());
+            return Err(super::last_time_error());
         }
     }
 
@@ -51,6 +89,16 @@ fn set_times(
     atime: Option<FileTime>,
     mtime: Option<FileTime>,
     symlink: bool,
+) -> io::Result<()> {
+    let p = CString::new(p.as_os_str().as_bytes())?;
+    set_times_cstr(&p, atime, mtime, symlink)
+}
+
+fn set_times_cstr(
+    p: &CStr,
+    atime: Option<FileTime>,
+    mtime: Option<FileTime>,
+    symlink: bool,
 ) -> io::Result<()> {
     // Attempt to use the `utimensat` syscall, but if it's not supported by the
     // current kernel then fall back to an older syscall.
@@ -61,48 +109,69 @@ fn set_times(
             0
         };
 
-        let p = CString::new(p.as_os_str().as_bytes())?;
-        let times = [super::to_timespec(&atime), super::to_timespec(&mtime)];
+        let times = [super::to_timespec(&atime)?, super::to_timespec(&mtime)?];
+        super::count_time_call();
         let rc = unsafe { func(libc::AT_FDCWD, p.as_ptr(), times.as_ptr(), flags) };
         if rc == 0 {
             return Ok(());
         } else {
-            return Err(io::Error::last_os_error());
+            return Err(super::last_time_error());
         }
     }
 
-    super::utimes::set_times(p, atime, mtime, symlink)
+    super::utimes::set_times_cstr(p, atime, mtime, symlink)
 }
 
-fn utimensat() -> Option<unsafe extern "C" fn(c_int, *const c_char, *const timespec, c_int) -> c_int>
-{
-    static ADDR: AtomicUsize = AtomicUsize::new(0);
-    unsafe {
-        fetch(&ADDR, CStr::from_bytes_with_nul_unchecked(b"utimensat\0"))
-            .map(|sym| mem::transmute(sym))
+pub fn set_time_args(p: &Path, atime: TimeArg, mtime: TimeArg, symlink: bool) -> io::Result<()> {
+    let p = CString::new(p.as_os_str().as_bytes())?;
+    if let Some(func) = utimensat() {
+        let flags = if symlink {
+            libc::AT_SYMLINK_NOFOLLOW
+        } else {
+            0
+        };
+
+        let times = [atime.to_timespec()?, mtime.to_timespec()?];
+        super::count_time_call();
+        let rc = unsafe { func(libc::AT_FDCWD, p.as_ptr(), times.as_ptr(), flags) };
+        if rc == 0 {
+            return Ok(());
+        } else {
+            return Err(super::last_time_error());
+        }
     }
+
+    super::utimes::set_time_args_cstr(&p, atime, mtime, symlink)
 }
 
-fn futimens() -> Option<unsafe extern "C" fn(c_int, *const timespec) -> c_int> {
-    static ADDR: AtomicUsize = AtomicUsize::new(0);
-    unsafe {
-        fetch(&ADDR, CStr::from_bytes_with_nul_unchecked(b"futimens\0"))
-            .map(|sym| mem::transmute(sym))
-    }
+pub fn supports_nanosecond_times() -> bool {
+    utimensat().is_some() && futimens().is_some()
 }
 
-fn fetch(cache: &AtomicUsize, name: &CStr) -> Option<usize> {
-    match cache.load(SeqCst) {
-        0 => {}
-        1 => return None,
-        n => return Some(n),
-    }
-    let sym = unsafe { libc::dlsym(libc::RTLD_DEFAULT, name.as_ptr() as *const _) };
-    let (val, ret) = if sym.is_null() {
-        (1, None)
+type UtimensatFn = unsafe extern "C" fn(c_int, *const c_char, *const timespec, c_int) -> c_int;
+type FutimensFn = unsafe extern "C" fn(c_int, *const timespec) -> c_int;
+
+fn utimensat() -> Option<UtimensatFn> {
+    static FUNC: OnceLock<Option<UtimensatFn>> = OnceLock::new();
+    *FUNC.get_or_init(|| unsafe {
+        fetch(CStr::from_bytes_with_nul_unchecked(b"utimensat\0"))
+            .map(|sym| mem::transmute::<*mut c_void, UtimensatFn>(sym))
+    })
+}
+
+fn futimens() -> Option<FutimensFn> {
+    static FUNC: OnceLock<Option<FutimensFn>> = OnceLock::new();
+    *FUNC.get_or_init(|| unsafe {
+        fetch(CStr::from_bytes_with_nul_unchecked(b"futimens\0"))
+            .map(|sym| mem::transmute::<*mut c_void, FutimensFn>(sym))
+    })
+}
+
+fn fetch(name: &CStr) -> Option<*mut c_void> {
+    let sym = unsafe { libc::dlsym(libc::RTLD_DEFAULT, name.as_ptr()) };
+    if sym.is_null() {
+        None
     } else {
-        (sym as usize, Some(sym as usize))
-    };
-    cache.store(val, SeqCst);
-    return ret;
+        Some(sym)
+    }
 }
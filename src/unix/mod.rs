@@ -1,89 +1,301 @@
 use crate::FileTime;
 use libc::{time_t, timespec};
+use std::convert::TryFrom;
 use std::fs;
+use std::io;
 use std::os::unix::prelude::*;
+#[cfg(any(
+    target_os = "linux",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+use std::sync::atomic::{AtomicBool, Ordering::SeqCst};
 
 cfg_if::cfg_if! {
     if #[cfg(target_os = "linux")] {
+        mod times;
         mod utimes;
         mod linux;
+        use self::times::to_timespec;
         pub use self::linux::*;
     } else if #[cfg(target_os = "android")] {
+        mod times;
         mod android;
+        use self::times::to_timespec;
         pub use self::android::*;
     } else if #[cfg(target_os = "macos")] {
+        mod times;
         mod utimes;
         mod macos;
+        use self::times::to_timespec;
         pub use self::macos::*;
+    } else if #[cfg(any(target_os = "freebsd",
+                        target_os = "netbsd",
+                        target_os = "openbsd"))] {
+        mod times;
+        mod utimes;
+        mod utimensat;
+        use self::times::to_timespec;
+        pub use self::utimensat::*;
     } else if #[cfg(any(target_os = "aix",
                         target_os = "solaris",
                         target_os = "illumos",
                         target_os = "emscripten",
-                        target_os = "freebsd",
-                        target_os = "netbsd",
-                        target_os = "openbsd",
-                        target_os = "haiku"))] {
+                        target_os = "haiku",
+                        target_os = "cygwin"))] {
+        mod times;
         mod utimensat;
+        use self::times::to_timespec;
         pub use self::utimensat::*;
     } else {
         mod utimes;
-        pub use self::utimes::*;
+        mod utimes_only;
+        pub use self::utimes_only::*;
+    }
+}
+
+/// Converts platform seconds to a `time_t`, failing rather than silently
+/// wrapping on platforms where `time_t` is only 32 bits wide.
+pub fn to_time_t(seconds: i64) -> io::Result<time_t> {
+    time_t::try_from(seconds).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "timestamp exceeds platform time_t range",
+        )
+    })
+}
+
+/// Returns the error from a failed call which sets times.
+///
+/// Where `time_t` is only 32 bits wide the kernel or libc reports `EOVERFLOW`
+/// for times after 2038, which is described the same way as times that
+/// `to_time_t` rejects up front. Whether the 64-bit `time_t` interfaces are
+/// used on such targets is decided by the `libc` crate's definitions.
+pub fn last_time_error() -> io::Error {
+    map_time_error(io::Error::last_os_error())
+}
+
+pub fn map_time_error(e: io::Error) -> io::Error {
+    if e.raw_os_error() == Some(libc::EOVERFLOW) {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "timestamp exceeds platform time_t range",
+        )
+    } else {
+        e
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    /// The number of calls made by this thread which read or set times.
+    pub static TIME_CALLS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// Records a call which reads or sets times, so tests can check how many
+/// calls a function makes.
+pub fn count_time_call() {
+    #[cfg(test)]
+    TIME_CALLS.with(|calls| calls.set(calls.get() + 1));
+}
+
+/// Runs `attempt`, switching permanently to `fallback` once `attempt` has
+/// reported `ENOSYS`.
+///
+/// This is used to fall back from `utimensat` and `futimens` to the less
+/// precise `utimes` family where the running kernel lacks them. The kernel's
+/// support can't change while the process is running, so remembering the
+/// failure avoids a wasted syscall on every call. The state is recorded in
+/// `unsupported`, which is specific to one call site.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+pub fn with_fallback(
+    unsupported: &AtomicBool,
+    attempt: impl FnOnce() -> io::Result<()>,
+    fallback: impl FnOnce() -> io::Result<()>,
+) -> io::Result<()> {
+    if !unsupported.load(SeqCst) {
+        match attempt() {
+            Err(e) if e.raw_os_error() == Some(libc::ENOSYS) => unsupported.store(true, SeqCst),
+            other => return other,
+        }
     }
+    fallback()
 }
 
-#[allow(dead_code)]
-fn to_timespec(ft: &Option<FileTime>) -> timespec {
+// Read-only and virtual filesystems such as `/proc` and `/sys` reject
+// attempts to set times with these.
+pub fn is_unsupported_error(e: &io::Error) -> bool {
+    match e.raw_os_error() {
+        Some(code) => code == libc::EROFS || code == libc::EPERM || code == libc::EOPNOTSUPP,
+        None => e.kind() == io::ErrorKind::Unsupported,
+    }
+}
+
+pub fn check_range(ft: &FileTime) -> io::Result<()> {
+    to_time_t(ft.seconds()).map(|_| ())
+}
+
+/// The special `tv_nsec` values for `utimensat` and `futimens`, which are
+/// re-exported from the crate root.
+///
+/// They're only defined for targets whose values have been checked against
+/// the system headers, as passing the wrong value sets a real time instead.
+pub mod utime {
     cfg_if::cfg_if! {
-        if #[cfg(any(target_os = "macos",
+        if #[cfg(any(target_vendor = "apple",
+                     target_os = "freebsd",
+                     target_os = "dragonfly",
                      target_os = "illumos",
-                     target_os = "freebsd"))] {
+                     target_os = "solaris",
+                     target_os = "hurd"))] {
             // https://github.com/apple/darwin-xnu/blob/a449c6a3b8014d9406c2ddbdc81795da24aa7443/bsd/sys/stat.h#L541
-            // https://github.com/illumos/illumos-gate/blob/master/usr/src/boot/sys/sys/stat.h#L312
             // https://svnweb.freebsd.org/base/head/sys/sys/stat.h?view=markup#l359
-            const UTIME_OMIT: i64 = -2;
-        } else if #[cfg(target_os = "openbsd")] {
+            // https://github.com/DragonFlyBSD/DragonFlyBSD/blob/master/sys/sys/stat.h
+            // https://github.com/illumos/illumos-gate/blob/master/usr/src/boot/sys/sys/stat.h#L312
+            // https://sourceware.org/git/?p=glibc.git;a=blob;f=sysdeps/mach/hurd/bits/stat.h
+            /// The special `tv_nsec` value which leaves a time unchanged when
+            /// passed to `utimensat` or `futimens`.
+            pub const UTIME_OMIT: i64 = -2;
+            /// The special `tv_nsec` value which sets a time to the current time
+            /// when passed to `utimensat` or `futimens`.
+            pub const UTIME_NOW: i64 = -1;
+        } else if #[cfg(any(target_os = "openbsd", target_os = "cygwin"))] {
             // https://github.com/openbsd/src/blob/master/sys/sys/stat.h#L189
-            const UTIME_OMIT: i64 = -1;
+            // https://github.com/cygwin/cygwin/blob/main/newlib/libc/include/sys/stat.h
+            /// The special `tv_nsec` value which leaves a time unchanged when
+            /// passed to `utimensat` or `futimens`.
+            pub const UTIME_OMIT: i64 = -1;
+            /// The special `tv_nsec` value which sets a time to the current time
+            /// when passed to `utimensat` or `futimens`.
+            pub const UTIME_NOW: i64 = -2;
         } else if #[cfg(target_os = "haiku")] {
             // https://git.haiku-os.org/haiku/tree/headers/posix/sys/stat.h?#n106
-            const UTIME_OMIT: i64 = 1000000001;
+            /// The special `tv_nsec` value which leaves a time unchanged when
+            /// passed to `utimensat` or `futimens`.
+            pub const UTIME_OMIT: i64 = 1000000001;
+            /// The special `tv_nsec` value which sets a time to the current time
+            /// when passed to `utimensat` or `futimens`.
+            pub const UTIME_NOW: i64 = 1000000000;
         } else if #[cfg(target_os = "aix")] {
             // AIX hasn't disclosed system header files yet.
             // https://github.com/golang/go/blob/master/src/cmd/vendor/golang.org/x/sys/unix/zerrors_aix_ppc64.go#L1007
-            const UTIME_OMIT: i64 = -3;
-        } else {
+            /// The special `tv_nsec` value which leaves a time unchanged when
+            /// passed to `utimensat` or `futimens`.
+            pub const UTIME_OMIT: i64 = -3;
+            /// The special `tv_nsec` value which sets a time to the current time
+            /// when passed to `utimensat` or `futimens`.
+            pub const UTIME_NOW: i64 = -2;
+        } else if #[cfg(target_os = "nto")] {
+            // https://github.com/rust-lang/libc/blob/main/src/unix/nto/mod.rs
+            /// The special `tv_nsec` value which leaves a time unchanged when
+            /// passed to `utimensat` or `futimens`.
+            pub const UTIME_OMIT: i64 = 0x4000_0002;
+            /// The special `tv_nsec` value which sets a time to the current time
+            /// when passed to `utimensat` or `futimens`.
+            pub const UTIME_NOW: i64 = 0x4000_0001;
+        } else if #[cfg(any(target_os = "linux",
+                            target_os = "android",
+                            target_os = "emscripten",
+                            target_os = "netbsd",
+                            target_os = "fuchsia",
+                            target_os = "vxworks"))] {
+            // https://github.com/torvalds/linux/blob/master/include/uapi/linux/stat.h
             // http://cvsweb.netbsd.org/bsdweb.cgi/src/sys/sys/stat.h?annotate=1.68.30.1
             // https://github.com/emscripten-core/emscripten/blob/master/system/include/libc/sys/stat.h#L71
-            const UTIME_OMIT: i64 = 1_073_741_822;
+            /// The special `tv_nsec` value which leaves a time unchanged when
+            /// passed to `utimensat` or `futimens`.
+            pub const UTIME_OMIT: i64 = 1_073_741_822;
+            /// The special `tv_nsec` value which sets a time to the current time
+            /// when passed to `utimensat` or `futimens`.
+            pub const UTIME_NOW: i64 = 1_073_741_823;
         }
     }
+}
+
+/// A time to set with `utimensat`, which can also leave the time unchanged or
+/// set it to the current time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeArg {
+    Omit,
+    Now,
+    At(FileTime),
+}
 
-    let mut ts: timespec = unsafe { std::mem::zeroed() };
-    if let &Some(ft) = ft {
-        ts.tv_sec = ft.seconds() as time_t;
+/// Converts the timestamp to a `timespec`, failing if the seconds don't fit in
+/// the platform's `time_t`.
+impl TryFrom<FileTime> for timespec {
+    type Error = io::Error;
+
+    fn try_from(ft: FileTime) -> io::Result<timespec> {
+        // Nanoseconds of a full second or more could otherwise collide with
+        // `UTIME_OMIT` or `UTIME_NOW`. No public constructor produces them.
+        if ft.nanoseconds() >= 1_000_000_000 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "nanoseconds must be less than one billion",
+            ));
+        }
+        let mut ts: timespec = unsafe { std::mem::zeroed() };
+        ts.tv_sec = to_time_t(ft.seconds())?;
         ts.tv_nsec = ft.nanoseconds() as _;
-    } else {
-        ts.tv_sec = 0;
-        ts.tv_nsec = UTIME_OMIT as _;
+        Ok(ts)
     }
+}
+
+/// Converts the timestamp to a `timeval`, truncating to microseconds and
+/// failing if the seconds don't fit in the platform's `time_t`.
+impl TryFrom<FileTime> for libc::timeval {
+    type Error = io::Error;
 
-    ts
+    fn try_from(ft: FileTime) -> io::Result<libc::timeval> {
+        Ok(libc::timeval {
+            tv_sec: to_time_t(ft.seconds())?,
+            tv_usec: (ft.nanoseconds() / 1000) as libc::suseconds_t,
+        })
+    }
 }
 
+// `std` reads `st_birthtime` on these platforms and `statx` on glibc-based
+// Linux. All Apple targets share Darwin's `stat`, including Mac Catalyst
+// (`target_os = "ios"` with `target_abi = "macabi"`) and the simulators.
+pub const SUPPORTS_CREATION_TIME: bool = cfg!(any(
+    all(target_os = "linux", target_env = "gnu"),
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "tvos",
+    target_os = "watchos",
+    target_os = "visionos",
+    target_os = "freebsd",
+    target_os = "openbsd",
+));
+
 pub fn from_last_modification_time(meta: &fs::Metadata) -> FileTime {
     FileTime {
         seconds: meta.mtime(),
-        nanos: meta.mtime_nsec() as u32,
+        nanos: crate::clamp_nsec(meta.mtime_nsec()),
     }
 }
 
 pub fn from_last_access_time(meta: &fs::Metadata) -> FileTime {
     FileTime {
         seconds: meta.atime(),
-        nanos: meta.atime_nsec() as u32,
+        nanos: crate::clamp_nsec(meta.atime_nsec()),
     }
 }
 
+pub fn from_status_change_time(meta: &fs::Metadata) -> Option<FileTime> {
+    Some(FileTime {
+        seconds: meta.ctime(),
+        nanos: crate::clamp_nsec(meta.ctime_nsec()),
+    })
+}
+
 pub fn from_creation_time(meta: &fs::Metadata) -> Option<FileTime> {
     #[cfg(target_os = "bitrig")]
     {
@@ -0,0 +1,33 @@
+//! The `timespec` pairs passed to `utimensat` and `futimens`, which can leave a
+//! time unchanged or set it to the current time as well as set it to a given
+//! time.
+
+use super::utime::{UTIME_NOW, UTIME_OMIT};
+use super::TimeArg;
+use crate::FileTime;
+use libc::timespec;
+use std::convert::TryFrom;
+use std::io;
+
+pub fn to_timespec(ft: &Option<FileTime>) -> io::Result<timespec> {
+    match *ft {
+        Some(ft) => timespec::try_from(ft),
+        None => Ok(special(UTIME_OMIT)),
+    }
+}
+
+impl TimeArg {
+    pub fn to_timespec(self) -> io::Result<timespec> {
+        match self {
+            TimeArg::Omit => Ok(special(UTIME_OMIT)),
+            TimeArg::Now => Ok(special(UTIME_NOW)),
+            TimeArg::At(ft) => timespec::try_from(ft),
+        }
+    }
+}
+
+fn special(nsec: i64) -> timespec {
+    let mut ts: timespec = unsafe { std::mem::zeroed() };
+    ts.tv_nsec = nsec as _;
+    ts
+}
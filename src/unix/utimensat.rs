@@ -1,10 +1,31 @@
+use super::TimeArg;
 use crate::FileTime;
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
 use std::fs::File;
 use std::io;
 use std::os::unix::prelude::*;
 use std::path::Path;
 
+cfg_if::cfg_if! {
+    if #[cfg(any(target_os = "freebsd", target_os = "netbsd", target_os = "openbsd"))] {
+        use std::sync::atomic::AtomicBool;
+        use std::sync::atomic::Ordering::SeqCst;
+
+        // As on Linux, the BSDs fall back to the `utimes` family if the
+        // running kernel reports `ENOSYS` for `futimens` or `utimensat`.
+        static HANDLE_UTIMENSAT_UNSUPPORTED: AtomicBool = AtomicBool::new(false);
+        static PATH_UTIMENSAT_UNSUPPORTED: AtomicBool = AtomicBool::new(false);
+
+        pub fn supports_nanosecond_times() -> bool {
+            !HANDLE_UTIMENSAT_UNSUPPORTED.load(SeqCst) && !PATH_UTIMENSAT_UNSUPPORTED.load(SeqCst)
+        }
+    } else {
+        pub fn supports_nanosecond_times() -> bool {
+            true
+        }
+    }
+}
+
 pub fn set_file_times(p: &Path, atime: FileTime, mtime: FileTime) -> io::Result<()> {
     set_times(p, Some(atime), Some(mtime), false)
 }
@@ -17,17 +38,46 @@ pub fn set_file_atime(p: &Path, atime: FileTime) -> io::Result<()> {
     set_times(p, Some(atime), None, false)
 }
 
+pub fn set_file_times_cstr(p: &CStr, atime: FileTime, mtime: FileTime) -> io::Result<()> {
+    set_times_cstr(p, Some(atime), Some(mtime), false)
+}
+
+pub fn set_file_times_at(
+    dirfd: RawFd,
+    p: &CStr,
+    atime: FileTime,
+    mtime: FileTime,
+    symlink: bool,
+) -> io::Result<()> {
+    set_times_at(dirfd, p, Some(atime), Some(mtime), symlink)
+}
+
 pub fn set_file_handle_times(
     f: &File,
     atime: Option<FileTime>,
     mtime: Option<FileTime>,
 ) -> io::Result<()> {
-    let times = [super::to_timespec(&atime), super::to_timespec(&mtime)];
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_os = "freebsd", target_os = "netbsd", target_os = "openbsd"))] {
+            super::with_fallback(
+                &HANDLE_UTIMENSAT_UNSUPPORTED,
+                || futimens(f, atime, mtime),
+                || super::utimes::set_file_handle_times(f, atime, mtime),
+            )
+        } else {
+            futimens(f, atime, mtime)
+        }
+    }
+}
+
+fn futimens(f: &File, atime: Option<FileTime>, mtime: Option<FileTime>) -> io::Result<()> {
+    let times = [super::to_timespec(&atime)?, super::to_timespec(&mtime)?];
+    super::count_time_call();
     let rc = unsafe { libc::futimens(f.as_raw_fd(), times.as_ptr()) };
     if rc == 0 {
         Ok(())
     } else {
-        Err(io::Error::last_os_error())
+        Err(super::last_time_error())
     }
 }
 
@@ -35,16 +85,70 @@ pub fn set_symlink_file_times(p: &Path, atime: FileTime, mtime: FileTime) -> io:
     set_times(p, Some(atime), Some(mtime), true)
 }
 
+pub fn set_time_args(p: &Path, atime: TimeArg, mtime: TimeArg, symlink: bool) -> io::Result<()> {
+    let p = CString::new(p.as_os_str().as_bytes())?;
+    let attempt = || {
+        let times = [atime.to_timespec()?, mtime.to_timespec()?];
+        utimensat(libc::AT_FDCWD, &p, &times, symlink)
+    };
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_os = "freebsd", target_os = "netbsd", target_os = "openbsd"))] {
+            super::with_fallback(
+                &PATH_UTIMENSAT_UNSUPPORTED,
+                attempt,
+                || super::utimes::set_time_args_cstr(&p, atime, mtime, symlink),
+            )
+        } else {
+            attempt()
+        }
+    }
+}
+
 fn set_times(
     p: &Path,
     atime: Option<FileTime>,
     mtime: Option<FileTime>,
     symlink: bool,
 ) -> io::Result<()> {
+    let p = CString::new(p.as_os_str().as_bytes())?;
+    set_times_cstr(&p, atime, mtime, symlink)
+}
+
+fn set_times_cstr(
+    p: &CStr,
+    atime: Option<FileTime>,
+    mtime: Option<FileTime>,
+    symlink: bool,
+) -> io::Result<()> {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_os = "freebsd", target_os = "netbsd", target_os = "openbsd"))] {
+            super::with_fallback(
+                &PATH_UTIMENSAT_UNSUPPORTED,
+                || set_times_at(libc::AT_FDCWD, p, atime, mtime, symlink),
+                || super::utimes::set_times_cstr(p, atime, mtime, symlink),
+            )
+        } else {
+            set_times_at(libc::AT_FDCWD, p, atime, mtime, symlink)
+        }
+    }
+}
+
+fn set_times_at(
+    dirfd: RawFd,
+    p: &CStr,
+    atime: Option<FileTime>,
+    mtime: Option<FileTime>,
+    symlink: bool,
+) -> io::Result<()> {
+    let times = [super::to_timespec(&atime)?, super::to_timespec(&mtime)?];
+    utimensat(dirfd, p, &times, symlink)
+}
+
+fn utimensat(dirfd: RawFd, p: &CStr, times: &[libc::timespec; 2], symlink: bool) -> io::Result<()> {
     let flags = if symlink {
         if cfg!(target_os = "emscripten") {
             return Err(io::Error::new(
-                io::ErrorKind::Other,
+                io::ErrorKind::Unsupported,
                 "emscripten does not support utimensat for symlinks",
             ));
         }
@@ -53,12 +157,11 @@ fn set_times(
         0
     };
 
-    let p = CString::new(p.as_os_str().as_bytes())?;
-    let times = [super::to_timespec(&atime), super::to_timespec(&mtime)];
-    let rc = unsafe { libc::utimensat(libc::AT_FDCWD, p.as_ptr(), times.as_ptr(), flags) };
+    super::count_time_call();
+    let rc = unsafe { libc::utimensat(dirfd, p.as_ptr(), times.as_ptr(), flags) };
     if rc == 0 {
         Ok(())
     } else {
-        Err(io::Error::last_os_error())
+        Err(super::last_time_error())
     }
 }
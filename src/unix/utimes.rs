@@ -1,27 +1,36 @@
+use super::TimeArg;
 use crate::FileTime;
+use std::convert::TryFrom;
+#[cfg(target_os = "linux")]
 use std::ffi::CString;
+use std::ffi::{CStr, OsStr};
 use std::fs;
 use std::io;
 use std::os::unix::prelude::*;
 use std::path::Path;
+use std::ptr;
 
-#[allow(dead_code)]
-pub fn set_file_times(p: &Path, atime: FileTime, mtime: FileTime) -> io::Result<()> {
-    set_times(p, Some(atime), Some(mtime), false)
-}
-
-#[allow(dead_code)]
-pub fn set_file_mtime(p: &Path, mtime: FileTime) -> io::Result<()> {
-    set_times(p, None, Some(mtime), false)
-}
-
-#[allow(dead_code)]
-pub fn set_file_atime(p: &Path, atime: FileTime) -> io::Result<()> {
-    set_times(p, Some(atime), None, false)
+// `utimes` can't be given a directory to resolve a path relative to, but on
+// Linux the directory can be reached through its `/proc/self/fd` entry, which
+// requires `/proc` to be mounted.
+#[cfg(target_os = "linux")]
+pub fn set_file_times_at(
+    dirfd: RawFd,
+    p: &CStr,
+    atime: FileTime,
+    mtime: FileTime,
+    symlink: bool,
+) -> io::Result<()> {
+    if p.to_bytes().starts_with(b"/") {
+        return set_times_cstr(p, Some(atime), Some(mtime), symlink);
+    }
+    let mut path = format!("/proc/self/fd/{}/", dirfd).into_bytes();
+    path.extend_from_slice(p.to_bytes());
+    let path = CString::new(path)?;
+    set_times_cstr(&path, Some(atime), Some(mtime), symlink)
 }
 
-#[cfg(not(target_env = "uclibc"))]
-#[allow(dead_code)]
+#[cfg(not(any(target_env = "uclibc", target_os = "horizon")))]
 pub fn set_file_handle_times(
     f: &fs::File,
     atime: Option<FileTime>,
@@ -31,17 +40,17 @@ pub fn set_file_handle_times(
         Some(pair) => pair,
         None => return Ok(()),
     };
-    let times = [to_timeval(&atime), to_timeval(&mtime)];
+    let times = [to_timeval(&atime)?, to_timeval(&mtime)?];
+    super::count_time_call();
     let rc = unsafe { libc::futimes(f.as_raw_fd(), times.as_ptr()) };
-    return if rc == 0 {
+    if rc == 0 {
         Ok(())
     } else {
-        Err(io::Error::last_os_error())
-    };
+        Err(super::last_time_error())
+    }
 }
 
 #[cfg(target_env = "uclibc")]
-#[allow(dead_code)]
 pub fn set_file_handle_times(
     f: &fs::File,
     atime: Option<FileTime>,
@@ -51,13 +60,28 @@ pub fn set_file_handle_times(
         Some(pair) => pair,
         None => return Ok(()),
     };
-    let times = [to_timespec(&atime), to_timespec(&mtime)];
+    let times = [to_timespec(&atime)?, to_timespec(&mtime)?];
+    super::count_time_call();
     let rc = unsafe { libc::futimens(f.as_raw_fd(), times.as_ptr()) };
-    return if rc == 0 {
+    if rc == 0 {
         Ok(())
     } else {
-        Err(io::Error::last_os_error())
-    };
+        Err(super::last_time_error())
+    }
+}
+
+// Horizon's newlib only provides `utimes`, with no way to set times through a
+// file descriptor or of a symlink itself.
+#[cfg(target_os = "horizon")]
+pub fn set_file_handle_times(
+    _f: &fs::File,
+    _atime: Option<FileTime>,
+    _mtime: Option<FileTime>,
+) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "setting times through a file handle is not supported on Horizon",
+    ))
 }
 
 fn get_times(
@@ -69,10 +93,12 @@ fn get_times(
         (Some(a), Some(b)) => (a, b),
         (None, None) => return Ok(None),
         (Some(a), None) => {
+            super::count_time_call();
             let meta = current()?;
             (a, FileTime::from_last_modification_time(&meta))
         }
         (None, Some(b)) => {
+            super::count_time_call();
             let meta = current()?;
             (FileTime::from_last_access_time(&meta), b)
         }
@@ -80,51 +106,93 @@ fn get_times(
     Ok(Some(pair))
 }
 
-#[allow(dead_code)]
-pub fn set_symlink_file_times(p: &Path, atime: FileTime, mtime: FileTime) -> io::Result<()> {
-    set_times(p, Some(atime), Some(mtime), true)
-}
-
-pub fn set_times(
-    p: &Path,
+pub fn set_times_cstr(
+    p: &CStr,
     atime: Option<FileTime>,
     mtime: Option<FileTime>,
     symlink: bool,
 ) -> io::Result<()> {
-    let (atime, mtime) = match get_times(atime, mtime, || p.metadata())? {
+    check_symlink(symlink)?;
+    let path = Path::new(OsStr::from_bytes(p.to_bytes()));
+    let (atime, mtime) = match get_times(atime, mtime, || path.metadata())? {
         Some(pair) => pair,
         None => return Ok(()),
     };
-    let p = CString::new(p.as_os_str().as_bytes())?;
-    let times = [to_timeval(&atime), to_timeval(&mtime)];
+    let times = [to_timeval(&atime)?, to_timeval(&mtime)?];
+    utimes(p, times.as_ptr(), symlink)
+}
+
+pub fn set_time_args_cstr(
+    p: &CStr,
+    atime: TimeArg,
+    mtime: TimeArg,
+    symlink: bool,
+) -> io::Result<()> {
+    // Passing no times sets both to the current time, which like `UTIME_NOW`
+    // only requires write access to the file rather than owning it.
+    if atime == TimeArg::Now && mtime == TimeArg::Now {
+        check_symlink(symlink)?;
+        return utimes(p, ptr::null(), symlink);
+    }
+    let now = FileTime::now();
+    set_times_cstr(p, atime.resolve(now), mtime.resolve(now), symlink)
+}
+
+impl TimeArg {
+    /// Replaces `Now` with `now`, as `utimes` can only set both times to the
+    /// current time at once.
+    fn resolve(self, now: FileTime) -> Option<FileTime> {
+        match self {
+            TimeArg::Omit => None,
+            TimeArg::Now => Some(now),
+            TimeArg::At(ft) => Some(ft),
+        }
+    }
+}
+
+fn check_symlink(symlink: bool) -> io::Result<()> {
+    if symlink && cfg!(target_os = "horizon") {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "setting the times of a symlink is not supported on Horizon",
+        ));
+    }
+    Ok(())
+}
+
+fn utimes(p: &CStr, times: *const libc::timeval, symlink: bool) -> io::Result<()> {
+    super::count_time_call();
+    #[cfg(not(target_os = "horizon"))]
     let rc = unsafe {
         if symlink {
-            libc::lutimes(p.as_ptr(), times.as_ptr())
+            libc::lutimes(p.as_ptr(), times)
         } else {
-            libc::utimes(p.as_ptr(), times.as_ptr())
+            libc::utimes(p.as_ptr(), times)
         }
     };
-    return if rc == 0 {
+    #[cfg(target_os = "horizon")]
+    let rc = {
+        let _ = symlink;
+        unsafe { libc::utimes(p.as_ptr(), times) }
+    };
+    if rc == 0 {
         Ok(())
     } else {
-        Err(io::Error::last_os_error())
-    };
+        Err(super::last_time_error())
+    }
 }
 
-fn to_timeval(ft: &FileTime) -> libc::timeval {
-    libc::timeval {
-        tv_sec: ft.seconds() as libc::time_t,
-        tv_usec: (ft.nanoseconds() / 1000) as libc::suseconds_t,
-    }
+fn to_timeval(ft: &FileTime) -> io::Result<libc::timeval> {
+    libc::timeval::try_from(*ft)
 }
 
 #[cfg(target_env = "uclibc")]
-fn to_timespec(ft: &FileTime) -> libc::timespec {
-    libc::timespec {
-        tv_sec: ft.seconds() as libc::time_t,
+fn to_timespec(ft: &FileTime) -> io::Result<libc::timespec> {
+    Ok(libc::timespec {
+        tv_sec: super::to_time_t(ft.seconds())?,
         #[cfg(all(target_arch = "x86_64", target_pointer_width = "32"))]
         tv_nsec: (ft.nanoseconds()) as i64,
         #[cfg(not(all(target_arch = "x86_64", target_pointer_width = "32")))]
         tv_nsec: (ft.nanoseconds()) as libc::c_long,
-    }
+    })
 }
@@ -0,0 +1,64 @@
+//! The backend for targets which only have the `utimes` family, built on the
+//! helpers which other backends fall back to when `utimensat` is missing.
+
+use super::utimes::{set_time_args_cstr, set_times_cstr};
+use super::TimeArg;
+use crate::FileTime;
+use std::ffi::{CStr, CString};
+use std::io;
+use std::os::unix::prelude::*;
+use std::path::Path;
+
+pub use super::utimes::set_file_handle_times;
+
+pub fn set_file_times(p: &Path, atime: FileTime, mtime: FileTime) -> io::Result<()> {
+    set_times(p, Some(atime), Some(mtime), false)
+}
+
+pub fn set_file_mtime(p: &Path, mtime: FileTime) -> io::Result<()> {
+    set_times(p, None, Some(mtime), false)
+}
+
+pub fn set_file_atime(p: &Path, atime: FileTime) -> io::Result<()> {
+    set_times(p, Some(atime), None, false)
+}
+
+pub fn set_file_times_cstr(p: &CStr, atime: FileTime, mtime: FileTime) -> io::Result<()> {
+    set_times_cstr(p, Some(atime), Some(mtime), false)
+}
+
+pub fn set_file_times_at(
+    _dirfd: RawFd,
+    _p: &CStr,
+    _atime: FileTime,
+    _mtime: FileTime,
+    _symlink: bool,
+) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "setting times relative to a directory requires utimensat",
+    ))
+}
+
+pub fn supports_nanosecond_times() -> bool {
+    false
+}
+
+pub fn set_symlink_file_times(p: &Path, atime: FileTime, mtime: FileTime) -> io::Result<()> {
+    set_times(p, Some(atime), Some(mtime), true)
+}
+
+pub fn set_time_args(p: &Path, atime: TimeArg, mtime: TimeArg, symlink: bool) -> io::Result<()> {
+    let p = CString::new(p.as_os_str().as_bytes())?;
+    set_time_args_cstr(&p, atime, mtime, symlink)
+}
+
+fn set_times(
+    p: &Path,
+    atime: Option<FileTime>,
+    mtime: Option<FileTime>,
+    symlink: bool,
+) -> io::Result<()> {
+    let p = CString::new(p.as_os_str().as_bytes())?;
+    set_times_cstr(&p, atime, mtime, symlink)
+}
@@ -19,6 +19,20 @@ pub fn set_file_atime(_p: &Path, _atime: FileTime) -> io::Result<()> {
     Err(io::Error::new(io::ErrorKind::Other, "Wasm not implemented"))
 }
 
+pub fn supports_nanosecond_times() -> bool {
+    false
+}
+
+pub fn is_unsupported_error(e: &io::Error) -> bool {
+    e.kind() == io::ErrorKind::Unsupported
+}
+
+pub fn check_range(_ft: &FileTime) -> io::Result<()> {
+    Ok(())
+}
+
+pub const SUPPORTS_CREATION_TIME: bool = false;
+
 pub fn from_last_modification_time(_meta: &fs::Metadata) -> FileTime {
     unimplemented!()
 }
@@ -27,6 +41,10 @@ pub fn from_last_access_time(_meta: &fs::Metadata) -> FileTime {
     unimplemented!()
 }
 
+pub fn from_status_change_time(_meta: &fs::Metadata) -> Option<FileTime> {
+    None
+}
+
 pub fn from_creation_time(_meta: &fs::Metadata) -> Option<FileTime> {
     unimplemented!()
 }
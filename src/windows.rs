@@ -4,8 +4,17 @@ use std::io;
 use std::os::windows::prelude::*;
 use std::path::Path;
 use std::ptr;
-use windows_sys::Win32::Foundation::{FILETIME, HANDLE};
+use std::thread;
+use std::time::Duration;
+use windows_sys::Win32::Foundation::{
+    ERROR_ACCESS_DENIED, ERROR_NOT_SUPPORTED, ERROR_SHARING_VIOLATION, ERROR_WRITE_PROTECT,
+    FILETIME, HANDLE, INVALID_HANDLE_VALUE, SYSTEMTIME,
+};
 use windows_sys::Win32::Storage::FileSystem::*;
+use windows_sys::Win32::System::SystemInformation::{
+    GetSystemTimeAsFileTime, GetSystemTimePreciseAsFileTime,
+};
+use windows_sys::Win32::System::Time::{FileTimeToSystemTime, SystemTimeToFileTime};
 
 pub fn set_file_times(p: &Path, atime: FileTime, mtime: FileTime) -> io::Result<()> {
     let f = OpenOptions::new()
@@ -15,6 +24,58 @@ pub fn set_file_times(p: &Path, atime: FileTime, mtime: FileTime) -> io::Result<
     set_file_handle_times(&f, Some(atime), Some(mtime))
 }
 
+pub fn set_file_times_posix_semantics(
+    p: &Path,
+    atime: FileTime,
+    mtime: FileTime,
+) -> io::Result<()> {
+    let f = OpenOptions::new()
+        .write(true)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS | FILE_FLAG_POSIX_SEMANTICS)
+        .open(p)?;
+    set_file_handle_times(&f, Some(atime), Some(mtime))
+}
+
+pub fn set_file_times_with_retry(
+    p: &Path,
+    atime: FileTime,
+    mtime: FileTime,
+    retries: u32,
+    delay: Duration,
+) -> io::Result<()> {
+    retry_on_sharing_violation(retries, delay, || set_file_times(p, atime, mtime))
+}
+
+// Antivirus and indexing services transiently hold files open without
+// sharing, so retry with an exponential backoff while that's the only
+// problem.
+pub fn retry_on_sharing_violation(
+    retries: u32,
+    mut delay: Duration,
+    mut f: impl FnMut() -> io::Result<()>,
+) -> io::Result<()> {
+    let mut attempts = 0;
+    loop {
+        match f() {
+            Err(e) if e.raw_os_error() == Some(ERROR_SHARING_VIOLATION as i32) => {
+                if attempts == retries {
+                    return Err(io::Error::new(
+                        e.kind(),
+                        format!(
+                            "file is in use by another process after {} retries: {}",
+                            retries, e
+                        ),
+                    ));
+                }
+                attempts += 1;
+                thread::sleep(delay);
+                delay = delay.checked_mul(2).unwrap_or(delay);
+            }
+            other => return other,
+        }
+    }
+}
+
 pub fn set_file_mtime(p: &Path, mtime: FileTime) -> io::Result<()> {
     let f = OpenOptions::new()
         .write(true)
@@ -36,12 +97,50 @@ pub fn set_file_handle_times(
     atime: Option<FileTime>,
     mtime: Option<FileTime>,
 ) -> io::Result<()> {
-    let atime = atime.map(to_filetime);
-    let mtime = mtime.map(to_filetime);
-    return unsafe {
+    set_handle_times(f, None, atime, mtime)
+}
+
+pub fn set_file_times_preserve_crtime(
+    p: &Path,
+    atime: FileTime,
+    mtime: FileTime,
+) -> io::Result<()> {
+    let f = OpenOptions::new()
+        .write(true)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+        .open(p)?;
+    let crtime = from_intervals(f.metadata()?.creation_time());
+    set_handle_times(&f, Some(crtime), Some(atime), Some(mtime))
+}
+
+pub fn update_file_times(
+    p: &Path,
+    f: impl FnOnce(&fs::Metadata) -> (Option<FileTime>, Option<FileTime>),
+) -> io::Result<()> {
+    let file = OpenOptions::new()
+        .write(true)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+        .open(p)?;
+    let (atime, mtime) = f(&file.metadata()?);
+    set_file_handle_times(&file, atime, mtime)
+}
+
+fn set_handle_times(
+    f: &File,
+    crtime: Option<FileTime>,
+    atime: Option<FileTime>,
+    mtime: Option<FileTime>,
+) -> io::Result<()> {
+    let crtime = crtime.map(to_filetime).transpose()?;
+    let atime = atime.map(to_filetime).transpose()?;
+    let mtime = mtime.map(to_filetime).transpose()?;
+    unsafe {
         let ret = SetFileTime(
             f.as_raw_handle() as HANDLE,
-            ptr::null(),
+            crtime
+                .as_ref()
+                .map(|p| p as *const FILETIME)
+                .unwrap_or(ptr::null()),
             atime
                 .as_ref()
                 .map(|p| p as *const FILETIME)
@@ -52,24 +151,86 @@ pub fn set_file_handle_times(
                 .unwrap_or(ptr::null()),
         );
         if ret != 0 {
-            Ok(())
-        } else {
-            Err(io::Error::last_os_error())
+            return Ok(());
         }
+    }
+
+    let err = io::Error::last_os_error();
+    if err.raw_os_error() == Some(ERROR_ACCESS_DENIED as i32) {
+        return Err(io::Error::new(
+            err.kind(),
+            format!(
+                "file handle lacks the FILE_WRITE_ATTRIBUTES access required to set its times: {}",
+                err
+            ),
+        ));
+    }
+    Err(err)
+}
+
+pub fn reopen_with_write_attributes(f: &File) -> io::Result<File> {
+    let handle = unsafe {
+        ReOpenFile(
+            f.as_raw_handle() as HANDLE,
+            FILE_READ_ATTRIBUTES | FILE_WRITE_ATTRIBUTES,
+            FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+            FILE_FLAG_BACKUP_SEMANTICS,
+        )
     };
+    if handle == INVALID_HANDLE_VALUE {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { File::from_raw_handle(handle as RawHandle) })
+}
 
-    fn to_filetime(ft: FileTime) -> FILETIME {
-        let intervals = ft.seconds() * (1_000_000_000 / 100) + ((ft.nanoseconds() as i64) / 100);
-        FILETIME {
-            dwLowDateTime: intervals as u32,
-            dwHighDateTime: (intervals >> 32) as u32,
-        }
+// File times are stored in 100ns intervals.
+pub fn supports_nanosecond_times() -> bool {
+    false
+}
+
+pub fn is_unsupported_error(e: &io::Error) -> bool {
+    match e.raw_os_error() {
+        Some(code) => code == ERROR_WRITE_PROTECT as i32 || code == ERROR_NOT_SUPPORTED as i32,
+        None => e.kind() == io::ErrorKind::Unsupported,
+    }
+}
+
+pub fn check_range(ft: &FileTime) -> io::Result<()> {
+    to_filetime(*ft).map(|_| ())
+}
+
+// A `FILETIME` is a non-negative count of 100ns intervals since 1601 which
+// must fit in an `i64`.
+fn to_intervals(ft: &FileTime) -> Option<i64> {
+    if ft.seconds() < 0 || ft.nanoseconds() >= 1_000_000_000 {
+        return None;
     }
+    ft.seconds()
+        .checked_mul(1_000_000_000 / 100)?
+        .checked_add((ft.nanoseconds() as i64) / 100)
+}
+
+// Fails rather than writing a wrapped value for timestamps that can't be
+// represented.
+fn to_filetime(ft: FileTime) -> io::Result<FILETIME> {
+    let intervals = to_intervals(&ft).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "timestamp out of valid Windows range",
+        )
+    })?;
+    Ok(FILETIME {
+        dwLowDateTime: intervals as u32,
+        dwHighDateTime: (intervals >> 32) as u32,
+    })
 }
 
 pub fn set_symlink_file_times(p: &Path, atime: FileTime, mtime: FileTime) -> io::Result<()> {
     use std::os::windows::fs::OpenOptionsExt;
 
+    // `FILE_FLAG_OPEN_REPARSE_POINT` opens the link itself rather than its
+    // target, and `FILE_FLAG_BACKUP_SEMANTICS` is needed as well to open
+    // directory symlinks and junctions.
     let f = OpenOptions::new()
         .write(true)
         .custom_flags(FILE_FLAG_OPEN_REPARSE_POINT | FILE_FLAG_BACKUP_SEMANTICS)
@@ -77,6 +238,24 @@ pub fn set_symlink_file_times(p: &Path, atime: FileTime, mtime: FileTime) -> io:
     set_file_handle_times(&f, Some(atime), Some(mtime))
 }
 
+pub fn set_times(
+    p: &Path,
+    follow: bool,
+    crtime: Option<FileTime>,
+    atime: Option<FileTime>,
+    mtime: Option<FileTime>,
+) -> io::Result<()> {
+    let flags = if follow {
+        FILE_FLAG_BACKUP_SEMANTICS
+    } else {
+        FILE_FLAG_OPEN_REPARSE_POINT | FILE_FLAG_BACKUP_SEMANTICS
+    };
+    let f = OpenOptions::new().write(true).custom_flags(flags).open(p)?;
+    set_handle_times(&f, crtime, atime, mtime)
+}
+
+pub const SUPPORTS_CREATION_TIME: bool = true;
+
 pub fn from_last_modification_time(meta: &fs::Metadata) -> FileTime {
     from_intervals(meta.last_write_time())
 }
@@ -85,11 +264,58 @@ pub fn from_last_access_time(meta: &fs::Metadata) -> FileTime {
     from_intervals(meta.last_access_time())
 }
 
+// Windows doesn't track when a file's metadata last changed.
+pub fn from_status_change_time(_meta: &fs::Metadata) -> Option<FileTime> {
+    None
+}
+
 pub fn from_creation_time(meta: &fs::Metadata) -> Option<FileTime> {
     Some(from_intervals(meta.creation_time()))
 }
 
-fn from_intervals(ticks: u64) -> FileTime {
+pub fn now() -> FileTime {
+    let mut ft = FILETIME {
+        dwLowDateTime: 0,
+        dwHighDateTime: 0,
+    };
+    unsafe { GetSystemTimeAsFileTime(&mut ft) };
+    from_filetime(ft)
+}
+
+pub fn now_precise() -> FileTime {
+    let mut ft = FILETIME {
+        dwLowDateTime: 0,
+        dwHighDateTime: 0,
+    };
+    unsafe { GetSystemTimePreciseAsFileTime(&mut ft) };
+    from_filetime(ft)
+}
+
+pub fn from_system_time_struct(st: &SYSTEMTIME) -> io::Result<FileTime> {
+    let mut ft = FILETIME {
+        dwLowDateTime: 0,
+        dwHighDateTime: 0,
+    };
+    if unsafe { SystemTimeToFileTime(st, &mut ft) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(from_filetime(ft))
+}
+
+pub fn to_system_time_struct(ft: &FileTime) -> io::Result<SYSTEMTIME> {
+    let ft = to_filetime(*ft)?;
+    let mut st: SYSTEMTIME = unsafe { std::mem::zeroed() };
+    if unsafe { FileTimeToSystemTime(&ft, &mut st) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(st)
+}
+
+fn from_filetime(ft: FILETIME) -> FileTime {
+    from_intervals(((ft.dwHighDateTime as u64) << 32) | ft.dwLowDateTime as u64)
+}
+
+pub fn from_intervals(ticks: u64) -> FileTime {
     // Windows write times are in 100ns intervals, so do a little math to
     // get it into the right representation.
     FileTime {